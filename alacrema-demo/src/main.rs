@@ -1,47 +1,74 @@
+use alacrema_templates::Templates;
+use alacrema_term::{TermCanvas, Terminal, canvas, input, render};
 use anathema::backend::tui::Style;
 use anathema::component::*;
-use anathema::default_widgets::Canvas;
 use anathema::prelude::*;
 use rand::prelude::*;
 use std::cmp::max;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 
-use anyhow::anyhow;
 use futures::prelude::*;
 use portable_pty::native_pty_system;
-use portable_pty::{CommandBuilder, PtySize};
+use portable_pty::{CommandBuilder, MasterPty, PtySize};
 use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The templates built into the binary, by file name.
+const TEMPLATES: &[(&str, &str)] = &[
+    ("ui.aml", include_str!("ui.aml")),
+    ("canvasFX.aml", include_str!("canvasFX.aml")),
+];
 
 const BUBBLE: &str = "·⋅◌⊙⊚⦾⁜";
 
 #[derive(State)]
 struct UIMainState {
     fps: Value<i32>,
-    stdout_test: Value<String>,
 }
 
 impl UIMainState {
-    fn _new() -> Self {
-        Self {
-            fps: 24.into(),
-            stdout_test: "".to_string().into(),
-        }
+    fn new() -> Self {
+        Self { fps: 24.into() }
     }
+}
 
-    fn with_test_output(test_output: String) -> Self {
+struct UIMain {
+    terminal: Arc<Mutex<Terminal>>,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    master: Box<dyn MasterPty + Send>,
+}
+
+impl UIMain {
+    fn new(
+        terminal: Arc<Mutex<Terminal>>,
+        writer: Arc<Mutex<Box<dyn Write + Send>>>,
+        master: Box<dyn MasterPty + Send>,
+    ) -> Self {
         Self {
-            fps: 24.into(),
-            stdout_test: test_output.into(),
+            terminal,
+            writer,
+            master,
         }
     }
-}
 
-struct UIMain {}
-
-impl UIMain {
-    fn new() -> Self {
-        Self {}
+    /// Match the pty to the canvas, the subprocess gets SIGWINCH from this.
+    fn fit_to(&mut self, rows: u16, cols: u16) {
+        let size = PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        if self.master.get_size().is_ok_and(|current| current == size) {
+            return;
+        }
+        if self.master.resize(size).is_ok() {
+            let mut terminal = self.terminal.lock().unwrap();
+            terminal.resize(rows as usize, cols as usize);
+        }
     }
 }
 
@@ -56,13 +83,17 @@ impl Component for UIMain {
         _context: Context<'_, '_, Self::State>,
         _dt: Duration,
     ) {
+        let mut size = None;
         let mut elements = interior.elements();
         elements
             .by_attribute("id", "canvasfx")
-            .each(|_e, attributes| {
+            .each(|e, attributes| {
                 attributes.set("fps", state.fps.copy_value());
-                attributes.set("output", state.stdout_test.to_ref().to_string());
+                size = Some(e.size());
             });
+        if let Some(size) = size {
+            self.fit_to(size.height, size.width);
+        }
     }
 
     fn on_key(
@@ -72,21 +103,29 @@ impl Component for UIMain {
         mut _interior: Children<'_, '_>,
         mut context: Context<'_, '_, Self::State>,
     ) {
+        // the demo's own keys are ones a shell has no use for
         match key.code {
-            KeyCode::Char('j') => {
+            KeyCode::Down if key.ctrl => {
                 let current = *state.fps.to_mut();
                 if current > 1 {
                     *state.fps.to_mut() -= 1;
                 }
             }
-            KeyCode::Char('q') => context.stop_runtime(),
-            KeyCode::Char('k') => {
+            KeyCode::Char('q') if key.ctrl => context.stop_runtime(),
+            KeyCode::Up if key.ctrl => {
                 let current = *state.fps.to_mut();
                 if current < 240 {
                     *state.fps.to_mut() += 1;
                 }
             }
-            _ => {}
+            // everything else is typed into the subprocess
+            _ => {
+                let modes = self.terminal.lock().unwrap().screen().modes();
+                if let Some(bytes) = input::encode_key(&key, &modes) {
+                    let mut writer = self.writer.lock().unwrap();
+                    _ = writer.write_all(&bytes).and_then(|_| writer.flush());
+                }
+            }
         }
     }
 }
@@ -101,6 +140,7 @@ impl CanvasFXState {
 }
 
 struct CanvasFX {
+    terminal: Arc<Mutex<Terminal>>,
     app_start: Instant,
     time_secs: f64,     // this value is only updated on an animation tick
     anim_tick: usize,   // this value goes up every n seconds
@@ -108,8 +148,9 @@ struct CanvasFX {
 }
 
 impl CanvasFX {
-    fn new() -> Self {
+    fn new(terminal: Arc<Mutex<Terminal>>) -> Self {
         Self {
+            terminal,
             app_start: Instant::now(),
             time_secs: 0.0f64,
             anim_tick: 0usize,
@@ -150,31 +191,11 @@ impl Component for CanvasFX {
             // let w = eib.to.x - eib.from.x;
             // let h = eib.to.y - eib.from.y;
 
-            let canvas = e.to::<Canvas>();
+            let canvas = e.to::<TermCanvas>();
             let style = Style::new();
             let mut rng = rand::rng();
 
-            let output = a
-                .get("output")
-                .unwrap()
-                .clone()
-                .as_str()
-                .unwrap()
-                .to_string();
-            let chars = output.chars();
-
-            let mut x_index = 0;
-            let mut y_index = 0;
-            // rectangles are weird huh
-            for char in chars {
-                if char == '\n' {
-                    y_index += 1;
-                    x_index = 0;
-                    continue;
-                }
-                canvas.put(char, style, (x_index, y_index));
-                x_index += 1;
-            }
+            render::paint(self.terminal.lock().unwrap().screen(), canvas);
 
             for y in 0..h {
                 for x in 0..w {
@@ -218,10 +239,42 @@ impl Component for CanvasFX {
     }
 }
 
+/// Feed everything the subprocess writes into the terminal as it arrives,
+/// until the pty closes.
+async fn stream_output(
+    reader: Box<dyn Read + Send>,
+    terminal: Arc<Mutex<Terminal>>,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+) {
+    let mut reader = smol::Unblock::new(reader);
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let replies = {
+            let mut terminal = terminal.lock().unwrap();
+            terminal.advance(&buf[..n]);
+            terminal.take_replies()
+        };
+        if !replies.is_empty() {
+            let mut writer = writer.lock().unwrap();
+            _ = writer.write_all(&replies).and_then(|_| writer.flush());
+        }
+    }
+}
+
 fn main() -> anyhow::Result<()> {
+    // overrides come from `$ALACREMA_TEMPLATES`, when it's set
+    let dir = std::env::var_os("ALACREMA_TEMPLATES").map(PathBuf::from);
+    let templates = Templates::new(TEMPLATES, dir);
+    templates.check(&[("main", "ui.aml"), ("canvasfx", "canvasFX.aml")])?;
+
     smol::block_on(async {
         let pty_system = native_pty_system();
 
+        // the canvas isn't laid out yet, UIMain resizes this on its first tick
         let pair = pty_system.openpty(PtySize {
             rows: 24,
             cols: 80,
@@ -229,11 +282,15 @@ fn main() -> anyhow::Result<()> {
             pixel_height: 0,
         })?;
 
-        let subprocess_cmd: Vec<OsString> = vec![
-            "ls".to_string().into(),
-            "--color=auto".to_string().into(),
-            "-lh".to_string().into(),
-        ];
+        // whatever is given on the command line runs instead of the listing
+        let mut subprocess_cmd: Vec<OsString> = std::env::args_os().skip(1).collect();
+        if subprocess_cmd.is_empty() {
+            subprocess_cmd = vec![
+                "ls".to_string().into(),
+                "--color=auto".to_string().into(),
+                "-lh".to_string().into(),
+            ];
+        }
         let mut cmd = CommandBuilder::from_argv(subprocess_cmd);
         if let Ok(cwd) = std::env::current_dir() {
             cmd.cwd(cwd);
@@ -243,33 +300,12 @@ fn main() -> anyhow::Result<()> {
         // NOTE: deadlock avoidance
         let mut subprocess = smol::unblock(move || slave.spawn_command(cmd)).await?;
 
-        {
-            // NOTE: deadlock avoidance
-            let writer = pair.master.take_writer()?;
-
-            // Explicitly generate EOF
-            drop(writer);
-        }
-
-        println!(
-            "subprocess status: {:?}",
-            smol::unblock(move || subprocess.wait().map_err(|e| anyhow!(": {}", e))).await?
-        );
-
+        // NOTE: keep the writer around, dropping it sends EOF to the subprocess
+        let writer = Arc::new(Mutex::new(pair.master.take_writer()?));
         let reader = pair.master.try_clone_reader()?;
 
-        // NOTE: take care. only after processes are done
-        drop(pair.master);
-
-        let mut output: String = Default::default();
-        let mut lines = smol::io::BufReader::new(smol::Unblock::new(reader)).lines();
-        while let Some(line) = lines.next().await {
-            let line = line.map_err(|e| anyhow!("problem reading line: {}", e))?;
-            for c in line.escape_debug() {
-                output.push(c);
-            }
-            output.push('\n');
-        }
+        let terminal = Arc::new(Mutex::new(Terminal::new(24, 80)));
+        let _output_task = smol::spawn(stream_output(reader, terminal.clone(), writer.clone()));
 
         let doc = Document::new("@main");
 
@@ -285,26 +321,33 @@ fn main() -> anyhow::Result<()> {
         };
 
         let mut builder = Runtime::builder(doc, &backend);
+        builder.register_widget::<TermCanvas>(canvas::TAG);
         builder
             .component(
                 "main",
-                "src/ui.aml",
-                UIMain::new(),
-                UIMainState::with_test_output(output),
+                templates.get("ui.aml")?,
+                UIMain::new(terminal.clone(), writer, pair.master),
+                UIMainState::new(),
             )
             .unwrap();
         builder
             .prototype(
                 "canvasfx",
-                "src/canvasFX.aml",
-                CanvasFX::new,
+                templates.get("canvasFX.aml")?,
+                move || CanvasFX::new(terminal.clone()),
                 CanvasFXState::new,
             )
             .unwrap();
-        builder
-            .finish(&mut backend, |runtime, backend| runtime.run(backend))
-            .unwrap();
+        let result = builder.finish(&mut backend, |runtime, backend| runtime.run(backend));
+        drop(backend);
 
-        Ok(())
+        // the ui is gone, don't leave the subprocess running without it
+        _ = subprocess.kill();
+
+        match result {
+            // quitting is reported as `Stop`
+            Ok(()) | Err(anathema::runtime::Error::Stop) => Ok(()),
+            Err(err) => Err(anyhow::anyhow!("{err}")),
+        }
     })
 }
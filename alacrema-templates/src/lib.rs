@@ -0,0 +1,110 @@
+//! Templates for anathema components, built into the binary and
+//! overridable one file at a time from a directory, so the look of a ui
+//! can be changed without building it again.
+
+use anathema::prelude::{Document, SourceKind};
+use anyhow::anyhow;
+use std::path::PathBuf;
+
+/// Finds each component's template: in the override directory if it has
+/// one by that name, otherwise the copy built in.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    builtin: &'static [(&'static str, &'static str)],
+    dir: Option<PathBuf>,
+}
+
+impl Templates {
+    /// `builtin` has the templates built into the binary by file name,
+    /// `dir` the ones to use instead, if any.
+    pub fn new(builtin: &'static [(&'static str, &'static str)], dir: Option<PathBuf>) -> Self {
+        Self { builtin, dir }
+    }
+
+    fn overridden(&self, file: &str) -> Option<PathBuf> {
+        let path = self.dir.as_ref()?.join(file);
+        path.is_file().then_some(path)
+    }
+
+    pub fn get(&self, file: &str) -> anyhow::Result<SourceKind> {
+        if let Some(path) = self.overridden(file) {
+            return Ok(SourceKind::Path(path));
+        }
+        let (_, template) = self
+            .builtin
+            .iter()
+            .find(|(name, _)| *name == file)
+            .ok_or_else(|| anyhow!("no template called {file}"))?;
+        Ok(SourceKind::Str(template.to_string()))
+    }
+
+    /// Parse the templates with `@main` as the root, given `(component,
+    /// file)` pairs, the way the runtime will. Anathema would show a broken
+    /// one in place of the ui, this says what's wrong before the terminal
+    /// is taken over. Nothing to do when only built in templates are used.
+    pub fn check(&self, components: &[(&str, &str)]) -> anyhow::Result<()> {
+        if !components
+            .iter()
+            .any(|(_, file)| self.overridden(file).is_some())
+        {
+            return Ok(());
+        }
+        let mut doc = Document::new("@main");
+        for (name, file) in components {
+            doc.add_component(*name, self.get(file)?)
+                .map_err(|err| anyhow!("template {}: {err}", err.path()))?;
+        }
+        match doc.compile() {
+            Ok(_) => Ok(()),
+            Err(err) if err.template_path.is_some() => {
+                Err(anyhow!("template {}: {err}", err.path()))
+            }
+            Err(err) => Err(anyhow!("template: {err}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILTIN: &[(&str, &str)] = &[("ui.aml", "text 'built in'")];
+
+    #[test]
+    fn an_override_wins_and_an_unknown_name_is_an_error() {
+        let dir = std::env::temp_dir().join(format!("alacrema-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ui.aml"), "text 'mine'").unwrap();
+
+        let plain = Templates::new(BUILTIN, None);
+        assert!(matches!(plain.get("ui.aml"), Ok(SourceKind::Str(s)) if s == "text 'built in'"));
+        let overridden = Templates::new(BUILTIN, Some(dir.clone()));
+        assert!(matches!(overridden.get("ui.aml"), Ok(SourceKind::Path(_))));
+        let Err(err) = plain.get("other.aml") else {
+            panic!("found a template that isn't there");
+        };
+        assert_eq!(err.to_string(), "no template called other.aml");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_broken_override_is_reported_with_its_file() {
+        let dir =
+            std::env::temp_dir().join(format!("alacrema-templates-broken-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let components = [("main", "ui.aml")];
+
+        std::fs::write(dir.join("ui.aml"), "text 'fine'").unwrap();
+        let templates = Templates::new(BUILTIN, Some(dir.clone()));
+        templates.check(&components).unwrap();
+
+        std::fs::write(dir.join("ui.aml"), "text [ 'unclosed'").unwrap();
+        let err = templates.check(&components).unwrap_err().to_string();
+        let path = dir.join("ui.aml");
+        assert!(
+            err.starts_with(&format!("template {}", path.display())),
+            "{err}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use crate::cell::Marks;
+use anathema::geometry::{LocalPos, Size};
+use anathema::resolver::AttributeStorage;
+use anathema::widgets::error::Result;
+use anathema::widgets::layout::{Constraints, LayoutCtx, PositionCtx};
+use anathema::widgets::paint::{Glyph, Glyphs, PaintCtx, SizePos};
+use anathema::widgets::{LayoutForEach, PaintChildren, PositionChildren, Style, Widget, WidgetId};
+use unicode_width::UnicodeWidthChar;
+
+/// What a canvas cell holds: a character, what combines with it, and how
+/// to draw them.
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    c: char,
+    marks: Marks,
+    style: Style,
+}
+
+#[derive(Debug, Default)]
+struct Buffer {
+    cells: Box<[Option<Cluster>]>,
+    size: Size,
+}
+
+impl Buffer {
+    fn new(size: Size) -> Self {
+        Self {
+            cells: vec![None; size.area()].into_boxed_slice(),
+            size,
+        }
+    }
+
+    fn index(&self, pos: LocalPos) -> Option<usize> {
+        (pos.x < self.size.width && pos.y < self.size.height).then(|| pos.to_index(self.size.width))
+    }
+
+    /// A copy of what fits of `self` in `size`.
+    fn resized(&self, size: Size) -> Self {
+        let mut resized = Self::new(size);
+        for (pos, cluster) in self.iter() {
+            if let Some(index) = resized.index(pos) {
+                resized.cells[index] = Some(*cluster);
+            }
+        }
+        resized
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (LocalPos, &Cluster)> + '_ {
+        let width = self.size.width.max(1);
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, cell)| {
+                let pos = LocalPos::new(index as u16 % width, index as u16 / width);
+                cell.as_ref().map(|cluster| (pos, cluster))
+            })
+    }
+}
+
+/// Anathema's canvas, except a cell holds a whole grapheme cluster: a
+/// character with accents combining onto it, or an emoji sequence. A wide
+/// character covers the cell after it, whatever was put there.
+///
+/// Registered with the runtime under a tag of its own, see [`TAG`].
+#[derive(Debug)]
+pub struct TermCanvas {
+    buffer: Buffer,
+    is_dirty: bool,
+}
+
+/// What templates call the [`TermCanvas`] widget.
+pub const TAG: &str = "termcanvas";
+
+impl TermCanvas {
+    pub fn put(&mut self, c: char, style: Style, pos: impl Into<LocalPos>) {
+        self.put_cluster(c, Marks::default(), style, pos);
+    }
+
+    /// Put `c` with `marks` combining onto it in one cell.
+    pub fn put_cluster(&mut self, c: char, marks: Marks, style: Style, pos: impl Into<LocalPos>) {
+        self.is_dirty = true;
+        if let Some(index) = self.buffer.index(pos.into()) {
+            self.buffer.cells[index] = Some(Cluster { c, marks, style });
+        }
+    }
+
+    /// The character at `pos` without its marks, if anything was put there.
+    pub fn get(&self, pos: impl Into<LocalPos>) -> Option<(char, Style)> {
+        let index = self.buffer.index(pos.into())?;
+        self.buffer.cells[index].map(|cluster| (cluster.c, cluster.style))
+    }
+
+    pub fn erase(&mut self, pos: impl Into<LocalPos>) {
+        self.is_dirty = true;
+        if let Some(index) = self.buffer.index(pos.into()) {
+            self.buffer.cells[index] = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.is_dirty = true;
+        self.buffer = Buffer::new(self.buffer.size);
+    }
+}
+
+impl Default for TermCanvas {
+    fn default() -> Self {
+        Self {
+            buffer: Buffer::new((32, 32).into()),
+            is_dirty: true,
+        }
+    }
+}
+
+impl Widget for TermCanvas {
+    fn layout<'bp>(
+        &mut self,
+        _: LayoutForEach<'_, 'bp>,
+        mut constraints: Constraints,
+        id: WidgetId,
+        ctx: &mut LayoutCtx<'_, 'bp>,
+    ) -> Result<Size> {
+        let attribs = ctx.attribute_storage.get(id);
+        if let Some(width) = attribs.get_as::<u16>("width") {
+            constraints.set_max_width(width);
+        }
+        if let Some(height) = attribs.get_as::<u16>("height") {
+            constraints.set_max_height(height);
+        }
+
+        let size = constraints.max_size();
+        if self.buffer.size != size {
+            self.buffer = self.buffer.resized(size);
+        }
+        Ok(size)
+    }
+
+    fn position<'bp>(
+        &mut self,
+        _: PositionChildren<'_, 'bp>,
+        _: WidgetId,
+        _: &AttributeStorage<'bp>,
+        _: PositionCtx,
+    ) {
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        _: PaintChildren<'_, 'bp>,
+        _: WidgetId,
+        _: &AttributeStorage<'bp>,
+        mut ctx: PaintCtx<'_, SizePos>,
+    ) {
+        // where the last wide character painted ends, what's under it
+        // doesn't get painted over it
+        let mut covered = LocalPos::new(0, 0);
+        let mut text = String::new();
+        for (pos, cluster) in self.buffer.iter() {
+            if pos.y == covered.y && pos.x < covered.x {
+                continue;
+            }
+            ctx.set_style(cluster.style, pos);
+            let next = if cluster.marks.is_empty() {
+                let width = cluster.c.width().unwrap_or(0) as u8;
+                ctx.place_glyph(Glyph::from_char(cluster.c, width), pos)
+            } else {
+                text.clear();
+                text.push(cluster.c);
+                text.extend(cluster.marks.iter());
+                ctx.place_glyphs(Glyphs::new(&text), pos)
+            };
+            if let Some(next) = next {
+                covered = next;
+            }
+        }
+    }
+
+    fn needs_reflow(&mut self) -> bool {
+        std::mem::take(&mut self.is_dirty)
+    }
+}
@@ -0,0 +1,124 @@
+/// A colour as the application asked for it. Mapping onto whatever the
+/// outer terminal supports is left to the renderer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// The outer terminal's own foreground/background.
+    #[default]
+    Default,
+    /// 0-15 are the classic ansi colours, 16-255 the xterm palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// SGR attributes that can be switched on and off independently.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attrs {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+/// Everything about a cell except its content.
+/// The terminal keeps one of these as its "pen" for new text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CellStyle {
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: Attrs,
+}
+
+/// How many characters can combine onto a cell's own: accents, variation
+/// selectors, the rest of an emoji sequence. Any more are dropped.
+pub const MAX_MARKS: usize = 4;
+
+/// The characters that go with a cell's character to make up one grapheme
+/// cluster. A fixed number of them, so cells stay `Copy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Marks([Option<char>; MAX_MARKS]);
+
+impl Marks {
+    /// Add `c` at the end. Returns false if there's no room left.
+    pub fn push(&mut self, c: char) -> bool {
+        match self.0.iter_mut().find(|mark| mark.is_none()) {
+            Some(mark) => {
+                *mark = Some(c);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0[0].is_none()
+    }
+
+    pub fn last(&self) -> Option<char> {
+        self.iter().last()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.iter().map_while(|mark| *mark)
+    }
+}
+
+/// How many columns a cell's character takes up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    #[default]
+    Single,
+    /// The first of the two columns a wide character takes up.
+    Wide,
+    /// The second column of the wide character to the left, with nothing
+    /// of its own in it.
+    Spacer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub c: char,
+    pub marks: Marks,
+    pub width: Width,
+    pub style: CellStyle,
+}
+
+impl Cell {
+    pub fn new(c: char, style: CellStyle) -> Self {
+        Self {
+            c,
+            marks: Marks::default(),
+            width: Width::Single,
+            style,
+        }
+    }
+
+    /// An empty cell. Erasing keeps the current background colour,
+    /// like xterm does.
+    pub fn blank(style: CellStyle) -> Self {
+        Self::new(
+            ' ',
+            CellStyle {
+                bg: style.bg,
+                ..CellStyle::default()
+            },
+        )
+    }
+
+    /// What the cell shows: its character and whatever combines with it.
+    /// Nothing for the second half of a wide character, the first half
+    /// has it.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        let own = (self.width != Width::Spacer).then_some(self.c);
+        own.into_iter().chain(self.marks.iter())
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::blank(CellStyle::default())
+    }
+}
@@ -0,0 +1,268 @@
+use crate::cell::{Cell, Width};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub cells: Vec<Cell>,
+    /// The text on this row continues on the next one because it ran
+    /// past the right margin (as opposed to an explicit newline).
+    pub wrapped: bool,
+}
+
+impl Row {
+    pub fn new(cols: usize, blank: Cell) -> Self {
+        Self {
+            cells: vec![blank; cols],
+            wrapped: false,
+        }
+    }
+
+    pub fn clear(&mut self, blank: Cell) {
+        self.cells.fill(blank);
+        self.wrapped = false;
+    }
+
+    /// The row as plain text, without trailing blanks.
+    pub fn text(&self) -> String {
+        let text: String = self.cells.iter().flat_map(Cell::chars).collect();
+        text.trim_end().to_string()
+    }
+}
+
+/// A fixed size block of rows, the visible part of a terminal.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    rows: Vec<Row>,
+    cols: usize,
+}
+
+impl Grid {
+    pub fn new(lines: usize, cols: usize) -> Self {
+        Self {
+            rows: vec![Row::new(cols, Cell::default()); lines],
+            cols,
+        }
+    }
+
+    pub fn lines(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn row(&self, y: usize) -> &Row {
+        &self.rows[y]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut Row {
+        &mut self.rows[y]
+    }
+
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        &mut self.rows[y].cells[x]
+    }
+
+    pub fn clear(&mut self, blank: Cell) {
+        for row in &mut self.rows {
+            row.clear(blank);
+        }
+    }
+
+    /// Move the rows in `top..=bottom` up by `n`, filling the gap at the
+    /// bottom with blank rows. Returns the rows that fell off the top.
+    pub fn scroll_up(&mut self, top: usize, bottom: usize, n: usize, blank: Cell) -> Vec<Row> {
+        let n = n.min(bottom + 1 - top);
+        let cols = self.cols;
+        let removed: Vec<Row> = self.rows.drain(top..top + n).collect();
+        let fresh = std::iter::repeat_with(|| Row::new(cols, blank)).take(n);
+        self.rows.splice(bottom + 1 - n..bottom + 1 - n, fresh);
+        removed
+    }
+
+    /// Move the rows in `top..=bottom` down by `n`, filling the gap at the
+    /// top with blank rows.
+    pub fn scroll_down(&mut self, top: usize, bottom: usize, n: usize, blank: Cell) {
+        let n = n.min(bottom + 1 - top);
+        let cols = self.cols;
+        self.rows.drain(bottom + 1 - n..=bottom);
+        let fresh = std::iter::repeat_with(|| Row::new(cols, blank)).take(n);
+        self.rows.splice(top..top, fresh);
+    }
+
+    /// Change the dimensions, rewrapping lines that wrapped at the old width
+    /// so their text flows into the new one. `cursor` is `(x, y)` and comes
+    /// back moved along with the text under it. Rows that no longer fit at
+    /// the top are returned.
+    pub fn reflow(
+        &mut self,
+        lines: usize,
+        cols: usize,
+        cursor: (usize, usize),
+    ) -> ((usize, usize), Vec<Row>) {
+        // join wrapped rows back into the logical lines they came from
+        let mut logical: Vec<Vec<Cell>> = vec![];
+        let mut cursor_at = (0, 0);
+        let mut continues = false;
+        for (y, row) in self.rows.drain(..).enumerate() {
+            if !continues {
+                logical.push(vec![]);
+            }
+            let index = logical.len() - 1;
+            let line = &mut logical[index];
+            if y == cursor.1 {
+                cursor_at = (index, line.len() + cursor.0);
+            }
+            continues = row.wrapped;
+            line.extend(row.cells);
+        }
+
+        let mut rows = vec![];
+        let mut new_cursor = (0, 0);
+        for (i, mut line) in logical.into_iter().enumerate() {
+            // trailing blanks were never written, they shouldn't wrap
+            let keep = if i == cursor_at.0 { cursor_at.1 } else { 0 };
+            while line.len() > keep && line.last() == Some(&Cell::default()) {
+                line.pop();
+            }
+            // a wide character can't be split over two rows, it moves
+            // down whole and leaves a blank at the end of the first
+            if cols > 1 {
+                let mut x = 0;
+                while x < line.len() {
+                    if line[x].width == Width::Wide && x % cols == cols - 1 {
+                        line.insert(x, Cell::default());
+                        if i == cursor_at.0 && x <= cursor_at.1 {
+                            cursor_at.1 += 1;
+                        }
+                        x += 1;
+                    }
+                    x += 1;
+                }
+            }
+            if i == cursor_at.0 {
+                let offset = cursor_at.1;
+                new_cursor = (offset % cols, rows.len() + offset / cols);
+            }
+            let mut chunks = line.chunks(cols).peekable();
+            if chunks.peek().is_none() {
+                rows.push(Row::new(cols, Cell::default()));
+            }
+            while let Some(chunk) = chunks.next() {
+                let mut row = Row::new(cols, Cell::default());
+                row.cells[..chunk.len()].copy_from_slice(chunk);
+                row.wrapped = chunks.peek().is_some();
+                rows.push(row);
+            }
+        }
+
+        // blank rows under the cursor can go, nothing pushes them back up
+        while rows.len() > lines.max(new_cursor.1 + 1)
+            && rows
+                .last()
+                .is_some_and(|row| row.cells.iter().all(|c| *c == Cell::default()))
+        {
+            rows.pop();
+        }
+
+        let overflow = rows.len().saturating_sub(lines);
+        let removed: Vec<Row> = rows.drain(..overflow).collect();
+        rows.resize(lines, Row::new(cols, Cell::default()));
+        self.rows = rows;
+        self.cols = cols;
+
+        let x = new_cursor.0.min(cols - 1);
+        let y = new_cursor.1.saturating_sub(overflow).min(lines - 1);
+        ((x, y), removed)
+    }
+
+    /// Change the dimensions, cropping or padding at the bottom and right.
+    pub fn resize(&mut self, lines: usize, cols: usize) {
+        for row in &mut self.rows {
+            row.cells.resize(cols, Cell::default());
+        }
+        self.rows.resize(lines, Row::new(cols, Cell::default()));
+        self.cols = cols;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellStyle;
+
+    /// A grid `cols` wide with a row for each of `rows`, marked wrapped
+    /// when it ends in `\`.
+    fn grid(cols: usize, rows: &[&str]) -> Grid {
+        let mut grid = Grid::new(rows.len(), cols);
+        for (y, text) in rows.iter().enumerate() {
+            let row = grid.row_mut(y);
+            let text = match text.strip_suffix('\\') {
+                Some(text) => {
+                    row.wrapped = true;
+                    text
+                }
+                None => text,
+            };
+            for (x, c) in text.chars().enumerate() {
+                row.cells[x] = Cell::new(c, CellStyle::default());
+            }
+        }
+        grid
+    }
+
+    fn texts(grid: &Grid) -> Vec<String> {
+        grid.rows().iter().map(Row::text).collect()
+    }
+
+    #[test]
+    fn widening_joins_wrapped_rows() {
+        let mut grid = grid(4, &["abcd\\", "ef", "gh", ""]);
+        let (cursor, overflow) = grid.reflow(4, 8, (1, 2));
+        assert_eq!(texts(&grid), ["abcdef", "gh", "", ""]);
+        assert!(!grid.row(0).wrapped);
+        // the cursor stays on the line it was on, which moved up
+        assert_eq!(cursor, (1, 1));
+        assert!(overflow.is_empty());
+    }
+
+    #[test]
+    fn narrowing_wraps_long_rows_and_pushes_the_top_out() {
+        let mut grid = grid(6, &["abcdef", "gh"]);
+        let (cursor, overflow) = grid.reflow(2, 3, (2, 1));
+        assert_eq!(texts(&grid), ["def", "gh"]);
+        assert_eq!(overflow.len(), 1);
+        assert_eq!(overflow[0].text(), "abc");
+        assert!(overflow[0].wrapped);
+        assert_eq!(cursor, (2, 1));
+    }
+
+    #[test]
+    fn the_cursor_follows_its_text_onto_the_next_row() {
+        let mut grid = grid(6, &["abcdef", "", ""]);
+        let (cursor, _) = grid.reflow(3, 4, (5, 0));
+        assert_eq!(texts(&grid), ["abcd", "ef", ""]);
+        assert_eq!(cursor, (1, 1));
+    }
+
+    #[test]
+    fn a_wide_character_moves_down_whole() {
+        let mut grid = grid(4, &["abc", ""]);
+        let wide = Cell {
+            width: Width::Wide,
+            ..Cell::new('界', CellStyle::default())
+        };
+        grid.row_mut(0).cells[2] = wide;
+        grid.row_mut(0).cells[3] = Cell {
+            width: Width::Spacer,
+            ..Cell::default()
+        };
+        grid.reflow(3, 3, (0, 1));
+        assert_eq!(texts(&grid), ["ab", "界", ""]);
+        assert_eq!(grid.row(1).cells[0].width, Width::Wide);
+    }
+}
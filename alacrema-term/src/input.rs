@@ -0,0 +1,296 @@
+use crate::term::{Modes, MouseTracking};
+use anathema::component::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseState};
+
+/// Translate a key press into the bytes a program in the terminal expects.
+///
+/// anathema's `KeyEvent` carries no alt flag, so this never produces meta
+/// sequences; see [`encode`] for that.
+pub fn encode_key(key: &KeyEvent, modes: &Modes) -> Option<Vec<u8>> {
+    encode(key.code, key.ctrl, false, modes)
+}
+
+/// Like [`encode_key`], with alt sending an `ESC` prefix.
+pub fn encode(code: KeyCode, ctrl: bool, alt: bool, modes: &Modes) -> Option<Vec<u8>> {
+    // xterm's modifier parameter: 1 + shift(1) + alt(2) + ctrl(4)
+    let modifier = 1 + if alt { 2 } else { 0 } + if ctrl { 4 } else { 0 };
+
+    let bytes = match code {
+        KeyCode::Char(c) => match ctrl.then(|| ctrl_char(c)).flatten() {
+            Some(b) => meta(alt, vec![b]),
+            None => meta(alt, c.to_string().into_bytes()),
+        },
+        KeyCode::CtrlC => meta(alt, vec![0x03]),
+        KeyCode::Enter if modes.linefeed_newline => meta(alt, b"\r\n".to_vec()),
+        KeyCode::Enter => meta(alt, b"\r".to_vec()),
+        KeyCode::Tab => meta(alt, b"\t".to_vec()),
+        KeyCode::BackTab => meta(alt, b"\x1b[Z".to_vec()),
+        KeyCode::Backspace if ctrl => meta(alt, vec![0x08]),
+        KeyCode::Backspace => meta(alt, vec![0x7f]),
+        KeyCode::Esc => meta(alt, vec![0x1b]),
+        KeyCode::Null => meta(alt, vec![0x00]),
+        KeyCode::Up => cursor_key(b'A', modifier, modes),
+        KeyCode::Down => cursor_key(b'B', modifier, modes),
+        KeyCode::Right => cursor_key(b'C', modifier, modes),
+        KeyCode::Left => cursor_key(b'D', modifier, modes),
+        KeyCode::Home => cursor_key(b'H', modifier, modes),
+        KeyCode::End => cursor_key(b'F', modifier, modes),
+        KeyCode::Insert => tilde_key(2, modifier),
+        KeyCode::Delete => tilde_key(3, modifier),
+        KeyCode::PageUp => tilde_key(5, modifier),
+        KeyCode::PageDown => tilde_key(6, modifier),
+        KeyCode::F(n @ 1..=4) => {
+            let last = (b'P' + (n - 1)) as char;
+            if modifier == 1 {
+                format!("\x1bO{last}").into_bytes()
+            } else {
+                format!("\x1b[1;{modifier}{last}").into_bytes()
+            }
+        }
+        KeyCode::F(n @ 5..=12) => {
+            let code = match n {
+                5 => 15,
+                6 => 17,
+                7 => 18,
+                8 => 19,
+                9 => 20,
+                10 => 21,
+                11 => 23,
+                _ => 24,
+            };
+            tilde_key(code, modifier)
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Translate a mouse event at column `x`, row `y` of the terminal into
+/// the report a program asked for with DEC modes 1000/1002/1003, in the
+/// SGR form if it also asked for 1006. `None` if it didn't ask for this
+/// kind of event, or the old form can't say where it happened.
+pub fn encode_mouse(mouse: &MouseEvent, (x, y): (u16, u16), modes: &Modes) -> Option<Vec<u8>> {
+    let tracking = modes.mouse;
+    // the button code, with 32 added for moves
+    let (code, release) = match mouse.state {
+        _ if tracking == MouseTracking::Off => return None,
+        MouseState::Down(button) => (button_code(button), false),
+        MouseState::Up(button) => (button_code(button), true),
+        MouseState::Drag(button) if tracking != MouseTracking::Click => {
+            (button_code(button) + 32, false)
+        }
+        MouseState::Move if tracking == MouseTracking::Motion => (3 + 32, false),
+        MouseState::ScrollUp => (64, false),
+        MouseState::ScrollDown => (65, false),
+        MouseState::ScrollLeft => (66, false),
+        MouseState::ScrollRight => (67, false),
+        _ => return None,
+    };
+    let (x, y) = (x as u32 + 1, y as u32 + 1);
+
+    if modes.sgr_mouse {
+        let last = if release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{code};{x};{y}{last}").into_bytes());
+    }
+    // the old form can't tell which button was let go of
+    let code = if release { 3 } else { code };
+    let [code, x, y] = [code, x, y].map(|n| u8::try_from(n + 32).ok());
+    Some(vec![0x1b, b'[', b'M', code?, x?, y?])
+}
+
+fn button_code(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    }
+}
+
+/// Alt is sent as an `ESC` in front of the key.
+fn meta(alt: bool, mut bytes: Vec<u8>) -> Vec<u8> {
+    if alt {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
+/// The C0 control code for ctrl + `c`, if there is one.
+fn ctrl_char(c: char) -> Option<u8> {
+    match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => Some(c as u8 - b'a' + 1),
+        '@' | ' ' | '2' => Some(0x00),
+        '[' | '3' => Some(0x1b),
+        '\\' | '4' => Some(0x1c),
+        ']' | '5' => Some(0x1d),
+        '^' | '6' => Some(0x1e),
+        '_' | '/' | '7' => Some(0x1f),
+        '?' | '8' => Some(0x7f),
+        _ => None,
+    }
+}
+
+fn cursor_key(last: u8, modifier: u8, modes: &Modes) -> Vec<u8> {
+    if modifier != 1 {
+        format!("\x1b[1;{modifier}{}", last as char).into_bytes()
+    } else if modes.app_cursor {
+        vec![0x1b, b'O', last]
+    } else {
+        vec![0x1b, b'[', last]
+    }
+}
+
+fn tilde_key(code: u8, modifier: u8) -> Vec<u8> {
+    if modifier == 1 {
+        format!("\x1b[{code}~").into_bytes()
+    } else {
+        format!("\x1b[{code};{modifier}~").into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anathema::component::KeyState;
+
+    fn key(code: KeyCode, ctrl: bool) -> Option<Vec<u8>> {
+        let key = KeyEvent {
+            code,
+            ctrl,
+            state: KeyState::Press,
+        };
+        encode_key(&key, &Modes::default())
+    }
+
+    fn click(
+        state: MouseState,
+        at: (u16, u16),
+        mouse: MouseTracking,
+        sgr: bool,
+    ) -> Option<Vec<u8>> {
+        let modes = Modes {
+            mouse,
+            sgr_mouse: sgr,
+            ..Modes::default()
+        };
+        let event = MouseEvent {
+            x: at.0,
+            y: at.1,
+            state,
+        };
+        encode_mouse(&event, at, &modes)
+    }
+
+    #[test]
+    fn ctrl_letters_are_control_codes() {
+        assert_eq!(key(KeyCode::Char('a'), false).unwrap(), b"a");
+        assert_eq!(key(KeyCode::Char('a'), true).unwrap(), [0x01]);
+        assert_eq!(key(KeyCode::Char('Z'), true).unwrap(), [0x1a]);
+        assert_eq!(key(KeyCode::Char('['), true).unwrap(), [0x1b]);
+        assert_eq!(key(KeyCode::Char(' '), true).unwrap(), [0x00]);
+        assert_eq!(key(KeyCode::CtrlC, false).unwrap(), [0x03]);
+        // nothing to send for these, so the character goes as is
+        assert_eq!(key(KeyCode::Char('é'), true).unwrap(), "é".as_bytes());
+        assert_eq!(key(KeyCode::Backspace, false).unwrap(), [0x7f]);
+        assert_eq!(key(KeyCode::Backspace, true).unwrap(), [0x08]);
+        let alt = encode(KeyCode::Char('x'), false, true, &Modes::default());
+        assert_eq!(alt.unwrap(), b"\x1bx");
+    }
+
+    #[test]
+    fn cursor_keys_follow_the_application_mode() {
+        let normal = Modes::default();
+        let app = Modes {
+            app_cursor: true,
+            ..normal
+        };
+        assert_eq!(
+            encode(KeyCode::Up, false, false, &normal).unwrap(),
+            b"\x1b[A"
+        );
+        assert_eq!(encode(KeyCode::Up, false, false, &app).unwrap(), b"\x1bOA");
+        assert_eq!(
+            encode(KeyCode::Home, false, false, &normal).unwrap(),
+            b"\x1b[H"
+        );
+        assert_eq!(encode(KeyCode::End, false, false, &app).unwrap(), b"\x1bOF");
+        // with a modifier it's the same either way
+        assert_eq!(
+            encode(KeyCode::Left, true, false, &app).unwrap(),
+            b"\x1b[1;5D"
+        );
+        assert_eq!(
+            encode(KeyCode::Right, false, true, &normal).unwrap(),
+            b"\x1b[1;3C"
+        );
+    }
+
+    #[test]
+    fn function_keys_and_the_editing_keys() {
+        assert_eq!(key(KeyCode::F(1), false).unwrap(), b"\x1bOP");
+        assert_eq!(key(KeyCode::F(4), false).unwrap(), b"\x1bOS");
+        assert_eq!(key(KeyCode::F(4), true).unwrap(), b"\x1b[1;5S");
+        assert_eq!(key(KeyCode::F(5), false).unwrap(), b"\x1b[15~");
+        assert_eq!(key(KeyCode::F(11), false).unwrap(), b"\x1b[23~");
+        assert_eq!(key(KeyCode::F(12), true).unwrap(), b"\x1b[24;5~");
+        assert_eq!(key(KeyCode::F(13), false), None);
+        assert_eq!(key(KeyCode::Delete, false).unwrap(), b"\x1b[3~");
+        assert_eq!(key(KeyCode::PageUp, false).unwrap(), b"\x1b[5~");
+        assert_eq!(key(KeyCode::BackTab, false).unwrap(), b"\x1b[Z");
+    }
+
+    #[test]
+    fn mouse_reports_come_in_the_form_asked_for() {
+        let down = MouseState::Down(MouseButton::Left);
+        let up = MouseState::Up(MouseButton::Right);
+        assert_eq!(click(down, (0, 0), MouseTracking::Off, false), None);
+        assert_eq!(
+            click(down, (4, 9), MouseTracking::Click, false).unwrap(),
+            [0x1b, b'[', b'M', 32, 37, 42]
+        );
+        assert_eq!(
+            click(down, (4, 9), MouseTracking::Click, true).unwrap(),
+            b"\x1b[<0;5;10M"
+        );
+        // only the sgr form says which button went up
+        assert_eq!(
+            click(up, (4, 9), MouseTracking::Click, true).unwrap(),
+            b"\x1b[<2;5;10m"
+        );
+        assert_eq!(
+            click(up, (4, 9), MouseTracking::Click, false).unwrap(),
+            [0x1b, b'[', b'M', 35, 37, 42]
+        );
+        assert_eq!(
+            click(MouseState::ScrollUp, (0, 0), MouseTracking::Click, true).unwrap(),
+            b"\x1b[<64;1;1M"
+        );
+    }
+
+    #[test]
+    fn moves_are_only_reported_when_asked_for() {
+        let drag = MouseState::Drag(MouseButton::Left);
+        assert_eq!(click(drag, (0, 0), MouseTracking::Click, true), None);
+        assert_eq!(
+            click(drag, (0, 0), MouseTracking::Drag, true).unwrap(),
+            b"\x1b[<32;1;1M"
+        );
+        assert_eq!(
+            click(MouseState::Move, (0, 0), MouseTracking::Drag, true),
+            None
+        );
+        assert_eq!(
+            click(MouseState::Move, (0, 0), MouseTracking::Motion, true).unwrap(),
+            b"\x1b[<35;1;1M"
+        );
+    }
+
+    #[test]
+    fn the_old_form_runs_out_past_column_223() {
+        let down = MouseState::Down(MouseButton::Left);
+        assert!(click(down, (222, 0), MouseTracking::Click, false).is_some());
+        assert_eq!(click(down, (223, 0), MouseTracking::Click, false), None);
+        assert_eq!(
+            click(down, (300, 0), MouseTracking::Click, true).unwrap(),
+            b"\x1b[<0;301;1M"
+        );
+    }
+}
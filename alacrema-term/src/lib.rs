@@ -0,0 +1,20 @@
+//! Terminal emulation for alacrema panes.
+//!
+//! `vte` does the escape sequence tokenising, everything it hands us is
+//! applied to a grid of styled cells that a pane can draw from.
+//! [`render`] puts those cells onto a [`TermCanvas`], which draws a cell's
+//! whole grapheme cluster where anathema's canvas only takes a `char`, and
+//! [`input`] turns key presses and mouse events into what the program on
+//! the other end expects to read.
+
+pub mod canvas;
+mod cell;
+mod grid;
+pub mod input;
+pub mod render;
+mod term;
+
+pub use canvas::TermCanvas;
+pub use cell::{Attrs, Cell, CellStyle, Color, MAX_MARKS, Marks, Width};
+pub use grid::{Grid, Row};
+pub use term::{Alerts, Cursor, DEFAULT_HISTORY_LIMIT, Modes, MouseTracking, Screen, Terminal};
@@ -0,0 +1,126 @@
+use crate::canvas::TermCanvas;
+use crate::cell::{Cell, CellStyle, Color, Width};
+use crate::term::Screen;
+use anathema::backend::tui::{Attributes, Style};
+use anathema::state::Color as TuiColor;
+
+/// Map an emulator colour onto anathema's.
+/// The 16 ansi colours stay named so the outer terminal's palette applies.
+pub fn to_tui_color(color: Color) -> TuiColor {
+    match color {
+        Color::Default => TuiColor::Reset,
+        Color::Indexed(n) => match n {
+            0 => TuiColor::Black,
+            1 => TuiColor::Red,
+            2 => TuiColor::Green,
+            3 => TuiColor::Yellow,
+            4 => TuiColor::Blue,
+            5 => TuiColor::Magenta,
+            6 => TuiColor::Cyan,
+            7 => TuiColor::Grey,
+            8 => TuiColor::DarkGrey,
+            9 => TuiColor::LightRed,
+            10 => TuiColor::LightGreen,
+            11 => TuiColor::LightYellow,
+            12 => TuiColor::LightBlue,
+            13 => TuiColor::LightMagenta,
+            14 => TuiColor::LightCyan,
+            15 => TuiColor::White,
+            n => TuiColor::AnsiVal(n),
+        },
+        Color::Rgb(r, g, b) => TuiColor::Rgb(r, g, b),
+    }
+}
+
+pub fn to_tui_style(style: &CellStyle) -> Style {
+    let mut out = Style::new();
+    out.fg = Some(to_tui_color(style.fg));
+    out.bg = Some(to_tui_color(style.bg));
+
+    let attrs = &style.attrs;
+    for (on, flag) in [
+        (attrs.bold, Attributes::BOLD),
+        (attrs.dim, Attributes::DIM),
+        (attrs.italic, Attributes::ITALIC),
+        (attrs.underline, Attributes::UNDERLINED),
+        (attrs.reverse, Attributes::REVERSED),
+        (attrs.strikethrough, Attributes::CROSSED_OUT),
+    ] {
+        if on {
+            out.attributes |= flag;
+        }
+    }
+    out
+}
+
+/// The cursor is drawn as the cell underneath it with reverse video flipped.
+pub fn cursor_style(cell: &Cell) -> Style {
+    let mut style = to_tui_style(&cell.style);
+    style.attributes.toggle(Attributes::REVERSED);
+    style
+}
+
+/// How a screen is drawn, besides what's in its cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Look {
+    /// Show the cursor, where the screen has one.
+    pub cursor: bool,
+    /// Draw everything faint, for a screen that isn't being typed into.
+    pub dim: bool,
+}
+
+impl Default for Look {
+    fn default() -> Self {
+        Self {
+            cursor: true,
+            dim: false,
+        }
+    }
+}
+
+/// Draw every cell of the screen onto the canvas, starting at the top left.
+/// Whatever doesn't fit the canvas is clipped.
+pub fn paint(screen: &Screen, canvas: &mut TermCanvas) {
+    paint_with(screen, canvas, Look::default());
+}
+
+/// [`paint`], the way `look` says.
+pub fn paint_with(screen: &Screen, canvas: &mut TermCanvas, look: Look) {
+    let blank = Cell::default();
+    for (y, row) in screen.display_rows().enumerate() {
+        // rows out of the scrollback can be shorter than the screen
+        for x in 0..screen.cols() {
+            let cell = row.cells.get(x).unwrap_or(&blank);
+            let mut style = to_tui_style(&cell.style);
+            if look.dim {
+                style.attributes |= Attributes::DIM;
+            }
+            let pos = (x as u16, y as u16);
+            match cell.width {
+                // the wide character before it covers it
+                Width::Spacer => {}
+                _ if cell.style.attrs.hidden => canvas.put(' ', style, pos),
+                _ => canvas.put_cluster(cell.c, cell.marks, style, pos),
+            }
+        }
+    }
+
+    if !look.cursor {
+        return;
+    }
+    if let Some(cursor) = screen.display_cursor() {
+        let row = screen.grid().row(cursor.y - screen.display_offset());
+        // on the second half of a wide character, the whole of it shows
+        let x = match row.cells[cursor.x].width {
+            Width::Spacer => cursor.x.saturating_sub(1),
+            _ => cursor.x,
+        };
+        let cell = row.cells[x];
+        canvas.put_cluster(
+            cell.c,
+            cell.marks,
+            cursor_style(&cell),
+            (x as u16, cursor.y as u16),
+        );
+    }
+}
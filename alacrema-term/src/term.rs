@@ -0,0 +1,1472 @@
+use crate::cell::{Cell, CellStyle, Color, Width};
+use crate::grid::{Grid, Row};
+use std::collections::VecDeque;
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Perform};
+
+const TAB_WIDTH: usize = 8;
+
+/// What emoji sequences are joined with: the character after it goes in
+/// the same cell.
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// Emoji modifiers, which change the emoji before them rather than take
+/// up columns of their own.
+const SKIN_TONES: std::ops::RangeInclusive<char> = '\u{1f3fb}'..='\u{1f3ff}';
+
+/// How many lines scrolled off the top are kept, unless told otherwise.
+pub const DEFAULT_HISTORY_LIMIT: usize = 2000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Which mouse events an application asked to be told about.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MouseTracking {
+    #[default]
+    Off,
+    /// 1000, buttons being pressed and released, and the wheel.
+    Click,
+    /// 1002, moving with a button held down as well.
+    Drag,
+    /// 1003, every move, buttons or not.
+    Motion,
+}
+
+/// Modes an application can switch with `CSI h` / `CSI l`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modes {
+    /// DECAWM, wrap to the next line at the right margin.
+    pub autowrap: bool,
+    /// DECOM, cursor addressing is relative to the scroll region.
+    pub origin: bool,
+    /// IRM, printing shifts the rest of the line right.
+    pub insert: bool,
+    /// LNM, line feed also returns the carriage.
+    pub linefeed_newline: bool,
+    /// DECTCEM
+    pub cursor_visible: bool,
+    /// DECCKM, cursor keys send `ESC O x` instead of `CSI x`.
+    pub app_cursor: bool,
+    /// Pasted text should come wrapped in `CSI 200 ~` / `CSI 201 ~`.
+    pub bracketed_paste: bool,
+    pub mouse: MouseTracking,
+    /// 1006, mouse events are reported as `CSI < b ; x ; y M` rather than
+    /// in single bytes, which run out past column 223.
+    pub sgr_mouse: bool,
+}
+
+impl Default for Modes {
+    fn default() -> Self {
+        Self {
+            autowrap: true,
+            origin: false,
+            insert: false,
+            linefeed_newline: false,
+            cursor_visible: true,
+            app_cursor: false,
+            bracketed_paste: false,
+            mouse: MouseTracking::Off,
+            sgr_mouse: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Charset {
+    #[default]
+    Ascii,
+    /// DEC special graphics, mostly used for line drawing.
+    DecSpecial,
+}
+
+impl Charset {
+    fn map(self, c: char) -> char {
+        match self {
+            Self::Ascii => c,
+            Self::DecSpecial => match c {
+                '_' => ' ',
+                '`' => '◆',
+                'a' => '▒',
+                'b' => '␉',
+                'c' => '␌',
+                'd' => '␍',
+                'e' => '␊',
+                'f' => '°',
+                'g' => '±',
+                'h' => '␤',
+                'i' => '␋',
+                'j' => '┘',
+                'k' => '┐',
+                'l' => '┌',
+                'm' => '└',
+                'n' => '┼',
+                'o' => '⎺',
+                'p' => '⎻',
+                'q' => '─',
+                'r' => '⎼',
+                's' => '⎽',
+                't' => '├',
+                'u' => '┤',
+                'v' => '┴',
+                'w' => '┬',
+                'x' => '│',
+                'y' => '≤',
+                'z' => '≥',
+                '{' => 'π',
+                '|' => '≠',
+                '}' => '£',
+                '~' => '·',
+                c => c,
+            },
+        }
+    }
+}
+
+/// What DECSC remembers.
+#[derive(Debug, Clone, Copy)]
+struct SavedCursor {
+    cursor: Cursor,
+    pending_wrap: bool,
+    pen: CellStyle,
+    origin: bool,
+    charsets: [Charset; 2],
+    active_charset: usize,
+}
+
+/// What happened in a terminal since it was last asked, so whoever isn't
+/// looking at it can still be told.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Alerts {
+    /// The program wrote something.
+    pub activity: bool,
+    /// The program rang the bell.
+    pub bell: bool,
+}
+
+/// A terminal emulator: feed it the bytes a program writes to its pty and
+/// read back the resulting screen.
+pub struct Terminal {
+    parser: vte::Parser,
+    screen: Screen,
+    activity: bool,
+    generation: u64,
+}
+
+impl Terminal {
+    pub fn new(lines: usize, cols: usize) -> Self {
+        Self {
+            parser: vte::Parser::new(),
+            screen: Screen::new(lines, cols),
+            activity: false,
+            generation: 0,
+        }
+    }
+
+    /// Process output from the program running in the terminal.
+    pub fn advance(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        self.activity = true;
+        self.generation += 1;
+        self.parser.advance(&mut self.screen, bytes);
+    }
+
+    /// Goes up whenever the screen may have changed, so anything keeping
+    /// a copy of it can tell when that copy is out of date.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Activity and bells since the last call.
+    pub fn take_alerts(&mut self) -> Alerts {
+        Alerts {
+            activity: std::mem::take(&mut self.activity),
+            bell: std::mem::take(&mut self.screen.bell),
+        }
+    }
+
+    pub fn resize(&mut self, lines: usize, cols: usize) {
+        self.generation += 1;
+        self.screen.resize(lines, cols);
+    }
+
+    /// Keep at most `lines` of scrollback, dropping the oldest beyond that.
+    pub fn set_history_limit(&mut self, lines: usize) {
+        self.screen.history_limit = lines;
+        self.screen.trim_history();
+    }
+
+    /// Move the view `delta` lines back into the scrollback, or towards the
+    /// live screen if negative. It stops at either end.
+    pub fn scroll_display(&mut self, delta: isize) {
+        let screen = &mut self.screen;
+        let offset = screen.display_offset.saturating_add_signed(delta);
+        screen.display_offset = offset.min(screen.history.len());
+        self.generation += 1;
+    }
+
+    /// Back to the live screen.
+    pub fn reset_display(&mut self) {
+        if self.screen.display_offset != 0 {
+            self.screen.display_offset = 0;
+            self.generation += 1;
+        }
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// The program is gone: back to the primary screen, with the modes it
+    /// switched set back, so the screen shows what was there before it and
+    /// the next one doesn't get mouse reports it never asked for.
+    pub fn reset_modes(&mut self) {
+        let screen = &mut self.screen;
+        if screen.alt_active {
+            screen.set_alternate_screen(false, true);
+        }
+        screen.modes = Modes::default();
+        self.generation += 1;
+    }
+
+    /// Answers to queries (cursor position, device attributes) that have to
+    /// be written back to the program.
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.screen.replies)
+    }
+}
+
+/// The state a [`Terminal`] builds up from the output it is fed.
+pub struct Screen {
+    primary: Grid,
+    alternate: Grid,
+    alt_active: bool,
+    cursor: Cursor,
+    // the cursor sits past the last column, the next print wraps first
+    pending_wrap: bool,
+    saved_primary: Option<SavedCursor>,
+    saved_alternate: Option<SavedCursor>,
+    pen: CellStyle,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    tabs: Vec<bool>,
+    modes: Modes,
+    charsets: [Charset; 2],
+    active_charset: usize,
+    last_char: Option<char>,
+    title: String,
+    // where the shell says it is (OSC 7) and how its last command exited
+    // (OSC 133)
+    cwd: Option<String>,
+    last_status: Option<i32>,
+    bell: bool,
+    replies: Vec<u8>,
+    // rows scrolled off the top of the primary grid, oldest first
+    history: VecDeque<Row>,
+    history_limit: usize,
+    // how many lines back into `history` the view is scrolled
+    display_offset: usize,
+    // lines that fell off the front of `history` so far
+    dropped: u64,
+}
+
+impl Screen {
+    fn new(lines: usize, cols: usize) -> Self {
+        let lines = lines.max(1);
+        let cols = cols.max(1);
+        Self {
+            primary: Grid::new(lines, cols),
+            alternate: Grid::new(lines, cols),
+            alt_active: false,
+            cursor: Cursor::default(),
+            pending_wrap: false,
+            saved_primary: None,
+            saved_alternate: None,
+            pen: CellStyle::default(),
+            scroll_top: 0,
+            scroll_bottom: lines - 1,
+            tabs: default_tabs(cols),
+            modes: Modes::default(),
+            charsets: [Charset::Ascii; 2],
+            active_charset: 0,
+            last_char: None,
+            title: String::new(),
+            cwd: None,
+            last_status: None,
+            bell: false,
+            replies: vec![],
+            history: VecDeque::new(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            display_offset: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn lines(&self) -> usize {
+        self.primary.lines()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.primary.cols()
+    }
+
+    /// The grid currently on display, either the primary or alternate one.
+    pub fn grid(&self) -> &Grid {
+        if self.alt_active {
+            &self.alternate
+        } else {
+            &self.primary
+        }
+    }
+
+    fn grid_mut(&mut self) -> &mut Grid {
+        if self.alt_active {
+            &mut self.alternate
+        } else {
+            &mut self.primary
+        }
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    pub fn modes(&self) -> Modes {
+        self.modes
+    }
+
+    /// Window title as set with OSC 0 or OSC 2.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The working directory the shell reported with OSC 7, if it does.
+    pub fn cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    /// The exit status of the last command, for shells that mark their
+    /// commands with OSC 133.
+    pub fn last_status(&self) -> Option<i32> {
+        self.last_status
+    }
+
+    pub fn is_alternate(&self) -> bool {
+        self.alt_active
+    }
+
+    /// How many lines of scrollback there are.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// How many lines were dropped off the front of the scrollback so
+    /// far. Buffer line numbers taken before then are this many lower
+    /// now that they went.
+    pub fn history_dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// How far back into the scrollback the view is, 0 when it's live.
+    pub fn display_offset(&self) -> usize {
+        self.display_offset
+    }
+
+    /// Scrollback and screen together, oldest first: how many lines there
+    /// are and the one at `index`.
+    pub fn buffer_len(&self) -> usize {
+        self.history.len() + self.lines()
+    }
+
+    pub fn buffer_line(&self, index: usize) -> Option<&Row> {
+        match index.checked_sub(self.history.len()) {
+            None => self.history.get(index),
+            Some(y) => self.grid().rows().get(y),
+        }
+    }
+
+    /// The buffer line shown at the top of the view.
+    pub fn display_top(&self) -> usize {
+        self.history.len() - self.display_offset
+    }
+
+    /// The rows in view, top to bottom: the end of the scrollback when
+    /// scrolled back, followed by as much of the grid as still fits.
+    /// Rows out of the scrollback can be narrower or wider than the screen.
+    pub fn display_rows(&self) -> impl Iterator<Item = &Row> {
+        let from_history = self.history.len() - self.display_offset;
+        self.history
+            .range(from_history..)
+            .chain(self.grid().rows())
+            .take(self.lines())
+    }
+
+    /// Where the cursor is drawn, if it is in view.
+    pub fn display_cursor(&self) -> Option<Cursor> {
+        let y = self.cursor.y + self.display_offset;
+        (self.modes.cursor_visible && y < self.lines()).then_some(Cursor {
+            x: self.cursor.x,
+            y,
+        })
+    }
+
+    /// The visible screen as plain text, one line per row.
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = self.grid().rows().iter().map(|row| row.text()).collect();
+        lines.join("\n")
+    }
+
+    /// Escape sequences that draw the visible screen, cursor and modes on
+    /// a fresh terminal of the same size.
+    pub fn dump(&self) -> Vec<u8> {
+        self.dump_styled(self.display_cursor(), |_, _| {})
+    }
+
+    /// [`Screen::dump`] with the cursor put at `cursor` instead, and every
+    /// cell's style passed through `restyle` first, which is how a
+    /// selection or search matches would show.
+    pub fn dump_styled(
+        &self,
+        cursor: Option<Cursor>,
+        restyle: impl Fn(Cursor, &mut CellStyle),
+    ) -> Vec<u8> {
+        let mut out = vec![];
+        let mut pen = CellStyle::default();
+        let mut cells = Vec::with_capacity(self.cols());
+        for (y, row) in self.display_rows().enumerate() {
+            cells.clear();
+            cells.extend((0..self.cols()).map(|x| {
+                let mut cell = row.cells.get(x).copied().unwrap_or_default();
+                restyle(Cursor { x, y }, &mut cell.style);
+                cell
+            }));
+            // a fresh terminal is blank already
+            let len = cells
+                .iter()
+                .rposition(|cell| *cell != Cell::default())
+                .map_or(0, |x| x + 1);
+            if len == 0 {
+                continue;
+            }
+            out.extend_from_slice(format!("\x1b[{};1H", y + 1).as_bytes());
+            for cell in &cells[..len] {
+                if cell.style != pen {
+                    pen = cell.style;
+                    write_sgr(&pen, &mut out);
+                }
+                let mut buf = [0u8; 4];
+                for c in cell.chars() {
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        write_sgr(&self.pen, &mut out);
+
+        let modes = self.modes;
+        let defaults = Modes::default();
+        for (on, default, code) in [
+            (modes.insert, defaults.insert, "4"),
+            (modes.linefeed_newline, defaults.linefeed_newline, "20"),
+            (modes.app_cursor, defaults.app_cursor, "?1"),
+            (modes.autowrap, defaults.autowrap, "?7"),
+            (modes.bracketed_paste, defaults.bracketed_paste, "?2004"),
+            (modes.sgr_mouse, defaults.sgr_mouse, "?1006"),
+        ] {
+            if on != default {
+                let end = if on { 'h' } else { 'l' };
+                out.extend_from_slice(format!("\x1b[{code}{end}").as_bytes());
+            }
+        }
+        let tracking = match modes.mouse {
+            MouseTracking::Off => None,
+            MouseTracking::Click => Some(1000),
+            MouseTracking::Drag => Some(1002),
+            MouseTracking::Motion => Some(1003),
+        };
+        if let Some(code) = tracking {
+            out.extend_from_slice(format!("\x1b[?{code}h").as_bytes());
+        }
+        match cursor {
+            Some(cursor) => {
+                out.extend_from_slice(format!("\x1b[{};{}H", cursor.y + 1, cursor.x + 1).as_bytes())
+            }
+            None => out.extend_from_slice(b"\x1b[?25l"),
+        }
+        out
+    }
+
+    fn resize(&mut self, lines: usize, cols: usize) {
+        let lines = lines.max(1);
+        let cols = cols.max(1);
+        if (lines, cols) == (self.lines(), self.cols()) {
+            return;
+        }
+
+        // full screen programs redraw the alternate screen themselves once
+        // they get SIGWINCH, only the primary one is worth reflowing
+        self.alternate.resize(lines, cols);
+        if self.alt_active {
+            // the cursor to come back to is the one saved on the way in
+            let saved = self.saved_primary.map_or(Cursor::default(), |s| s.cursor);
+            let ((x, y), overflow) = self.primary.reflow(lines, cols, (saved.x, saved.y));
+            self.push_history(overflow);
+            if let Some(saved) = self.saved_primary.as_mut() {
+                saved.cursor = Cursor { x, y };
+            }
+            self.cursor.x = self.cursor.x.min(cols - 1);
+            self.cursor.y = self.cursor.y.min(lines - 1);
+        } else {
+            let cursor = (self.cursor.x, self.cursor.y);
+            let ((x, y), overflow) = self.primary.reflow(lines, cols, cursor);
+            self.push_history(overflow);
+            self.cursor = Cursor { x, y };
+        }
+        if let Some(saved) = self.saved_alternate.as_mut() {
+            saved.cursor.x = saved.cursor.x.min(cols - 1);
+            saved.cursor.y = saved.cursor.y.min(lines - 1);
+        }
+
+        self.tabs = default_tabs(cols);
+        self.scroll_top = 0;
+        self.scroll_bottom = lines - 1;
+        self.pending_wrap = false;
+    }
+
+    fn reset(&mut self) {
+        // how much scrollback to keep is the pane's setting, not the
+        // program's
+        let history_limit = self.history_limit;
+        let dropped = self.dropped + self.history.len() as u64;
+        *self = Self::new(self.lines(), self.cols());
+        self.history_limit = history_limit;
+        self.dropped = dropped;
+    }
+
+    fn blank(&self) -> Cell {
+        Cell::blank(self.pen)
+    }
+
+    fn goto(&mut self, x: usize, y: usize) {
+        let (top, bottom) = if self.modes.origin {
+            (self.scroll_top, self.scroll_bottom)
+        } else {
+            (0, self.lines() - 1)
+        };
+        self.cursor.x = x.min(self.cols() - 1);
+        self.cursor.y = (top + y).min(bottom);
+        self.pending_wrap = false;
+    }
+
+    fn goto_x(&mut self, x: usize) {
+        self.cursor.x = x.min(self.cols() - 1);
+        self.pending_wrap = false;
+    }
+
+    fn goto_y(&mut self, y: usize) {
+        let x = self.cursor.x;
+        self.goto(x, y);
+    }
+
+    fn move_up(&mut self, n: usize) {
+        let limit = if self.cursor.y >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        };
+        self.cursor.y = self.cursor.y.saturating_sub(n).max(limit);
+        self.pending_wrap = false;
+    }
+
+    fn move_down(&mut self, n: usize) {
+        let limit = if self.cursor.y <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.lines() - 1
+        };
+        self.cursor.y = (self.cursor.y + n).min(limit);
+        self.pending_wrap = false;
+    }
+
+    fn carriage_return(&mut self) {
+        self.cursor.x = 0;
+        self.pending_wrap = false;
+    }
+
+    fn linefeed(&mut self) {
+        if self.cursor.y == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.y + 1 < self.lines() {
+            self.cursor.y += 1;
+        }
+        self.pending_wrap = false;
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.y == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor.y = self.cursor.y.saturating_sub(1);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn tab_forward(&mut self, n: usize) {
+        for _ in 0..n {
+            let next = (self.cursor.x + 1..self.cols()).find(|&x| self.tabs[x]);
+            self.cursor.x = next.unwrap_or(self.cols() - 1);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn tab_backward(&mut self, n: usize) {
+        for _ in 0..n {
+            let prev = (0..self.cursor.x).rev().find(|&x| self.tabs[x]);
+            self.cursor.x = prev.unwrap_or(0);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn save_cursor(&mut self) {
+        let saved = SavedCursor {
+            cursor: self.cursor,
+            pending_wrap: self.pending_wrap,
+            pen: self.pen,
+            origin: self.modes.origin,
+            charsets: self.charsets,
+            active_charset: self.active_charset,
+        };
+        if self.alt_active {
+            self.saved_alternate = Some(saved);
+        } else {
+            self.saved_primary = Some(saved);
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = if self.alt_active {
+            self.saved_alternate
+        } else {
+            self.saved_primary
+        };
+        let Some(saved) = saved else {
+            self.cursor = Cursor::default();
+            self.pending_wrap = false;
+            return;
+        };
+        self.cursor.x = saved.cursor.x.min(self.cols() - 1);
+        self.cursor.y = saved.cursor.y.min(self.lines() - 1);
+        self.pending_wrap = saved.pending_wrap;
+        self.pen = saved.pen;
+        self.modes.origin = saved.origin;
+        self.charsets = saved.charsets;
+        self.active_charset = saved.active_charset;
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        let (top, bottom, blank) = (self.scroll_top, self.scroll_bottom, self.blank());
+        let removed = self.grid_mut().scroll_up(top, bottom, n, blank);
+        // only whole screen scrolls on the primary grid are history,
+        // the rest is programs moving their own text around
+        if top == 0 && !self.alt_active {
+            self.push_history(removed);
+        }
+    }
+
+    fn push_history(&mut self, rows: Vec<Row>) {
+        for mut row in rows {
+            // trailing blanks are what most lines are made of
+            let len = row
+                .cells
+                .iter()
+                .rposition(|cell| *cell != Cell::default())
+                .map_or(0, |x| x + 1);
+            row.cells.truncate(len);
+            row.cells.shrink_to_fit();
+            self.history.push_back(row);
+            // someone looking back keeps seeing the same lines
+            if self.display_offset > 0 {
+                self.display_offset += 1;
+            }
+        }
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        let excess = self.history.len().saturating_sub(self.history_limit);
+        self.history.drain(..excess);
+        self.dropped += excess as u64;
+        self.display_offset = self.display_offset.min(self.history.len());
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let (top, bottom, blank) = (self.scroll_top, self.scroll_bottom, self.blank());
+        self.grid_mut().scroll_down(top, bottom, n, blank);
+    }
+
+    fn write_char(&mut self, c: char) {
+        let c = self.charsets[self.active_charset].map(c);
+        let wide = c.width() == Some(2) && self.cols() > 1;
+
+        let joined = self.previous_cell().is_some_and(|cell| {
+            cell.marks.last() == Some(ZERO_WIDTH_JOINER)
+                || c.width().unwrap_or(0) == 0
+                || SKIN_TONES.contains(&c)
+        });
+        if joined {
+            // nothing to combine with at the start of a line, and too
+            // many marks are dropped
+            if let Some(cell) = self.previous_cell() {
+                cell.marks.push(c);
+            }
+            return;
+        }
+        if c.width().unwrap_or(0) == 0 {
+            return;
+        }
+
+        // a wide character doesn't get split over the margin, it goes on
+        // the next line
+        if wide && self.cursor.x + 1 == self.cols() && self.modes.autowrap {
+            self.pending_wrap = true;
+        }
+        if self.pending_wrap {
+            if self.modes.autowrap {
+                let y = self.cursor.y;
+                self.grid_mut().row_mut(y).wrapped = true;
+                self.carriage_return();
+                self.linefeed();
+            }
+            self.pending_wrap = false;
+        }
+
+        let (y, cols) = (self.cursor.y, self.cols());
+        let width = if wide { 2 } else { 1 };
+        // without autowrap it's squeezed in before the margin instead
+        let x = self.cursor.x.min(cols - width);
+        let mut cell = Cell::new(c, self.pen);
+        let (blank, insert) = (self.blank(), self.modes.insert);
+        let row = self.grid_mut().row_mut(y);
+        if insert {
+            row.cells.splice(x..x, std::iter::repeat_n(blank, width));
+            row.cells.truncate(cols);
+            repair_wide(row, blank);
+        }
+        clear_wide_at(row, x, blank);
+        if wide {
+            clear_wide_at(row, x + 1, blank);
+            cell.width = Width::Wide;
+            row.cells[x + 1] = Cell {
+                width: Width::Spacer,
+                ..Cell::new(' ', cell.style)
+            };
+        }
+        row.cells[x] = cell;
+
+        if x + width < cols {
+            self.cursor.x = x + width;
+        } else {
+            self.cursor.x = cols - 1;
+            self.pending_wrap = true;
+        }
+        self.last_char = Some(c);
+    }
+
+    /// The cell last written to, for a combining character to go into.
+    fn previous_cell(&mut self) -> Option<&mut Cell> {
+        let (x, y) = (self.cursor.x, self.cursor.y);
+        let x = if self.pending_wrap {
+            x
+        } else {
+            x.checked_sub(1)?
+        };
+        let row = self.grid_mut().row_mut(y);
+        let x = match row.cells[x].width {
+            Width::Spacer => x.checked_sub(1)?,
+            _ => x,
+        };
+        Some(&mut row.cells[x])
+    }
+
+    fn erase_in_line(&mut self, mode: u16) {
+        let (x, y, cols, blank) = (self.cursor.x, self.cursor.y, self.cols(), self.blank());
+        let range = match mode {
+            0 => x..cols,
+            1 => 0..x + 1,
+            2 => 0..cols,
+            _ => return,
+        };
+        let row = self.grid_mut().row_mut(y);
+        row.cells[range].fill(blank);
+        if mode != 1 {
+            row.wrapped = false;
+        }
+    }
+
+    fn erase_in_display(&mut self, mode: u16) {
+        let (y, blank) = (self.cursor.y, self.blank());
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                for row in y + 1..self.lines() {
+                    self.grid_mut().row_mut(row).clear(blank);
+                }
+            }
+            1 => {
+                self.erase_in_line(1);
+                for row in 0..y {
+                    self.grid_mut().row_mut(row).clear(blank);
+                }
+            }
+            2 => self.grid_mut().clear(blank),
+            // xterm's "erase saved lines"
+            3 => {
+                self.dropped += self.history.len() as u64;
+                self.history.clear();
+                self.display_offset = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn insert_blanks(&mut self, n: usize) {
+        let (x, y, cols, blank) = (self.cursor.x, self.cursor.y, self.cols(), self.blank());
+        let n = n.min(cols - x);
+        let row = self.grid_mut().row_mut(y);
+        row.cells.splice(x..x, std::iter::repeat_n(blank, n));
+        row.cells.truncate(cols);
+        repair_wide(row, blank);
+        self.pending_wrap = false;
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let (x, y, cols, blank) = (self.cursor.x, self.cursor.y, self.cols(), self.blank());
+        let n = n.min(cols - x);
+        let row = self.grid_mut().row_mut(y);
+        row.cells.drain(x..x + n);
+        row.cells.resize(cols, blank);
+        repair_wide(row, blank);
+        self.pending_wrap = false;
+    }
+
+    fn erase_chars(&mut self, n: usize) {
+        let (x, y, cols, blank) = (self.cursor.x, self.cursor.y, self.cols(), self.blank());
+        let end = (x + n).min(cols);
+        let row = self.grid_mut().row_mut(y);
+        row.cells[x..end].fill(blank);
+        repair_wide(row, blank);
+        self.pending_wrap = false;
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        let y = self.cursor.y;
+        if y < self.scroll_top || y > self.scroll_bottom {
+            return;
+        }
+        let (bottom, blank) = (self.scroll_bottom, self.blank());
+        self.grid_mut().scroll_down(y, bottom, n, blank);
+        self.carriage_return();
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        let y = self.cursor.y;
+        if y < self.scroll_top || y > self.scroll_bottom {
+            return;
+        }
+        let (bottom, blank) = (self.scroll_bottom, self.blank());
+        self.grid_mut().scroll_up(y, bottom, n, blank);
+        self.carriage_return();
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let bottom = bottom.min(self.lines() - 1);
+        if top >= bottom {
+            return;
+        }
+        self.scroll_top = top;
+        self.scroll_bottom = bottom;
+        self.goto(0, 0);
+    }
+
+    fn set_alternate_screen(&mut self, on: bool, save_cursor: bool) {
+        if on == self.alt_active {
+            return;
+        }
+        if on {
+            if save_cursor {
+                self.save_cursor();
+            }
+            self.alt_active = true;
+            let blank = self.blank();
+            self.alternate.clear(blank);
+        } else {
+            self.alt_active = false;
+            if save_cursor {
+                self.restore_cursor();
+            }
+        }
+        self.pending_wrap = false;
+    }
+
+    // like xterm, switching any of them off stops the reporting, whichever
+    // one was on
+    fn set_mouse_tracking(&mut self, tracking: MouseTracking, on: bool) {
+        self.modes.mouse = if on { tracking } else { MouseTracking::Off };
+    }
+
+    fn set_mode(&mut self, params: &Params, private: bool, on: bool) {
+        for param in params.iter() {
+            match (private, param[0]) {
+                (false, 4) => self.modes.insert = on,
+                (false, 20) => self.modes.linefeed_newline = on,
+                (true, 1) => self.modes.app_cursor = on,
+                (true, 6) => {
+                    self.modes.origin = on;
+                    self.goto(0, 0);
+                }
+                (true, 7) => self.modes.autowrap = on,
+                (true, 25) => self.modes.cursor_visible = on,
+                (true, 47) | (true, 1047) => self.set_alternate_screen(on, false),
+                (true, 1048) => {
+                    if on {
+                        self.save_cursor();
+                    } else {
+                        self.restore_cursor();
+                    }
+                }
+                (true, 1049) => self.set_alternate_screen(on, true),
+                (true, 1000) => self.set_mouse_tracking(MouseTracking::Click, on),
+                (true, 1002) => self.set_mouse_tracking(MouseTracking::Drag, on),
+                (true, 1003) => self.set_mouse_tracking(MouseTracking::Motion, on),
+                (true, 1006) => self.modes.sgr_mouse = on,
+                (true, 2004) => self.modes.bracketed_paste = on,
+                _ => {}
+            }
+        }
+    }
+
+    fn sgr(&mut self, params: &Params) {
+        if params.is_empty() {
+            self.pen = CellStyle::default();
+            return;
+        }
+
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let attrs = &mut self.pen.attrs;
+            match param {
+                [0] => self.pen = CellStyle::default(),
+                [1] => attrs.bold = true,
+                [2] => attrs.dim = true,
+                [3] => attrs.italic = true,
+                [4, 0] => attrs.underline = false,
+                [4, ..] | [21] => attrs.underline = true,
+                [5] | [6] => attrs.blink = true,
+                [7] => attrs.reverse = true,
+                [8] => attrs.hidden = true,
+                [9] => attrs.strikethrough = true,
+                [22] => {
+                    attrs.bold = false;
+                    attrs.dim = false;
+                }
+                [23] => attrs.italic = false,
+                [24] => attrs.underline = false,
+                [25] => attrs.blink = false,
+                [27] => attrs.reverse = false,
+                [28] => attrs.hidden = false,
+                [29] => attrs.strikethrough = false,
+                [n @ 30..=37] => self.pen.fg = Color::Indexed((n - 30) as u8),
+                [38, rest @ ..] => {
+                    if let Some(color) = extended_color(rest, &mut iter) {
+                        self.pen.fg = color;
+                    }
+                }
+                [39] => self.pen.fg = Color::Default,
+                [n @ 40..=47] => self.pen.bg = Color::Indexed((n - 40) as u8),
+                [48, rest @ ..] => {
+                    if let Some(color) = extended_color(rest, &mut iter) {
+                        self.pen.bg = color;
+                    }
+                }
+                [49] => self.pen.bg = Color::Default,
+                [n @ 90..=97] => self.pen.fg = Color::Indexed((n - 90 + 8) as u8),
+                [n @ 100..=107] => self.pen.bg = Color::Indexed((n - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+
+    fn report(&mut self, reply: &str) {
+        self.replies.extend_from_slice(reply.as_bytes());
+    }
+}
+
+/// Parse the colour following a 38/48, either from the colon separated
+/// subparameters (`38:2::r:g:b`) or from the following parameters
+/// (`38;5;n`).
+fn extended_color<'a>(sub: &[u16], iter: &mut impl Iterator<Item = &'a [u16]>) -> Option<Color> {
+    if !sub.is_empty() {
+        return match sub {
+            [5, n, ..] => Some(Color::Indexed(*n as u8)),
+            [2, _, r, g, b, ..] => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+            [2, r, g, b] => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+            _ => None,
+        };
+    }
+
+    match iter.next()? {
+        [5] => Some(Color::Indexed(iter.next()?[0] as u8)),
+        [2] => {
+            let r = iter.next()?[0] as u8;
+            let g = iter.next()?[0] as u8;
+            let b = iter.next()?[0] as u8;
+            Some(Color::Rgb(r, g, b))
+        }
+        _ => None,
+    }
+}
+
+/// The SGR sequence that sets the pen to `style` from scratch.
+fn write_sgr(style: &CellStyle, out: &mut Vec<u8>) {
+    let attrs = style.attrs;
+    let mut params = vec!["0".to_string()];
+    for (on, param) in [
+        (attrs.bold, "1"),
+        (attrs.dim, "2"),
+        (attrs.italic, "3"),
+        (attrs.underline, "4"),
+        (attrs.blink, "5"),
+        (attrs.reverse, "7"),
+        (attrs.hidden, "8"),
+        (attrs.strikethrough, "9"),
+    ] {
+        if on {
+            params.push(param.to_string());
+        }
+    }
+    for (color, base) in [(style.fg, 38), (style.bg, 48)] {
+        match color {
+            Color::Default => {}
+            Color::Indexed(n) => params.push(format!("{base};5;{n}")),
+            Color::Rgb(r, g, b) => params.push(format!("{base};2;{r};{g};{b}")),
+        }
+    }
+    out.extend_from_slice(format!("\x1b[{}m", params.join(";")).as_bytes());
+}
+
+/// Writing over half of a wide character takes the other half with it.
+fn clear_wide_at(row: &mut Row, x: usize, blank: Cell) {
+    match row.cells[x].width {
+        Width::Wide if x + 1 < row.cells.len() => row.cells[x + 1] = blank,
+        Width::Spacer if x > 0 => row.cells[x - 1] = blank,
+        _ => {}
+    }
+}
+
+/// Blank out halves of wide characters whose other half was shifted away
+/// or erased.
+fn repair_wide(row: &mut Row, blank: Cell) {
+    for x in 0..row.cells.len() {
+        let lone = match row.cells[x].width {
+            Width::Single => false,
+            Width::Wide => row
+                .cells
+                .get(x + 1)
+                .is_none_or(|next| next.width != Width::Spacer),
+            Width::Spacer => x == 0 || row.cells[x - 1].width != Width::Wide,
+        };
+        if lone {
+            row.cells[x] = blank;
+        }
+    }
+}
+
+fn default_tabs(cols: usize) -> Vec<bool> {
+    (0..cols).map(|x| x > 0 && x % TAB_WIDTH == 0).collect()
+}
+
+/// The `index`th parameter, or `default` when it is missing or zero.
+fn arg(params: &Params, index: usize, default: u16) -> u16 {
+    match params.iter().nth(index).map(|p| p[0]) {
+        None | Some(0) => default,
+        Some(n) => n,
+    }
+}
+
+impl Perform for Screen {
+    fn print(&mut self, c: char) {
+        self.write_char(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            0x07 => self.bell = true,
+            0x08 => {
+                self.cursor.x = self.cursor.x.saturating_sub(1);
+                self.pending_wrap = false;
+            }
+            0x09 => self.tab_forward(1),
+            0x0a..=0x0c => {
+                self.linefeed();
+                if self.modes.linefeed_newline {
+                    self.carriage_return();
+                }
+            }
+            0x0d => self.carriage_return(),
+            0x0e => self.active_charset = 1,
+            0x0f => self.active_charset = 0,
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            [b"0" | b"2", rest @ ..] => {
+                let title: Vec<String> = rest
+                    .iter()
+                    .map(|part| String::from_utf8_lossy(part).into_owned())
+                    .collect();
+                self.title = title.join(";");
+            }
+            // file://host/path, the host is left out
+            [b"7", url, ..] => {
+                let url = String::from_utf8_lossy(url);
+                if let Some(rest) = url.strip_prefix("file://") {
+                    let path = rest.find('/').map_or("", |i| &rest[i..]);
+                    self.cwd = Some(percent_decode(path));
+                }
+            }
+            // D is the end of a command's output, with its exit status
+            [b"133", mark, rest @ ..] if mark.first() == Some(&b'D') => {
+                self.last_status = rest
+                    .first()
+                    .and_then(|status| std::str::from_utf8(status).ok()?.parse().ok());
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+
+        let private = intermediates.first() == Some(&b'?');
+        let n = arg(params, 0, 1) as usize;
+
+        match (action, intermediates) {
+            ('@', []) => self.insert_blanks(n),
+            ('A', []) => self.move_up(n),
+            ('B' | 'e', []) => self.move_down(n),
+            ('C' | 'a', []) => {
+                let x = self.cursor.x + n;
+                self.goto_x(x);
+            }
+            ('D', []) => {
+                let x = self.cursor.x.saturating_sub(n);
+                self.goto_x(x);
+            }
+            ('E', []) => {
+                self.move_down(n);
+                self.carriage_return();
+            }
+            ('F', []) => {
+                self.move_up(n);
+                self.carriage_return();
+            }
+            ('G' | '`', []) => self.goto_x(n - 1),
+            ('H' | 'f', []) => {
+                let y = arg(params, 0, 1) as usize - 1;
+                let x = arg(params, 1, 1) as usize - 1;
+                self.goto(x, y);
+            }
+            ('I', []) => self.tab_forward(n),
+            ('J', []) => self.erase_in_display(arg(params, 0, 0)),
+            ('K', []) => self.erase_in_line(arg(params, 0, 0)),
+            ('L', []) => self.insert_lines(n),
+            ('M', []) => self.delete_lines(n),
+            ('P', []) => self.delete_chars(n),
+            ('S', []) => self.scroll_up(n),
+            ('T', []) => self.scroll_down(n),
+            ('X', []) => self.erase_chars(n),
+            ('Z', []) => self.tab_backward(n),
+            ('b', []) => {
+                if let Some(c) = self.last_char {
+                    for _ in 0..n {
+                        self.write_char(c);
+                    }
+                }
+            }
+            ('c', []) => self.report("\x1b[?1;2c"),
+            ('c', [b'>']) => self.report("\x1b[>0;0;0c"),
+            ('d', []) => self.goto_y(n - 1),
+            ('g', []) => match arg(params, 0, 0) {
+                0 => {
+                    let x = self.cursor.x;
+                    self.tabs[x] = false;
+                }
+                3 => self.tabs.fill(false),
+                _ => {}
+            },
+            ('h', _) => self.set_mode(params, private, true),
+            ('l', _) => self.set_mode(params, private, false),
+            ('m', []) => self.sgr(params),
+            ('n', []) => match arg(params, 0, 0) {
+                5 => self.report("\x1b[0n"),
+                6 => {
+                    let top = if self.modes.origin {
+                        self.scroll_top
+                    } else {
+                        0
+                    };
+                    // DECRC can put the cursor back above an origin mode
+                    // region that moved down since
+                    let y = self.cursor.y.saturating_sub(top) + 1;
+                    let reply = format!("\x1b[{y};{}R", self.cursor.x + 1);
+                    self.report(&reply);
+                }
+                _ => {}
+            },
+            ('r', []) => {
+                let top = arg(params, 0, 1) as usize - 1;
+                let bottom = arg(params, 1, self.lines() as u16) as usize - 1;
+                self.set_scroll_region(top, bottom);
+            }
+            ('s', []) => self.save_cursor(),
+            ('u', []) => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        match (intermediates, byte) {
+            ([], b'7') => self.save_cursor(),
+            ([], b'8') => self.restore_cursor(),
+            ([], b'D') => self.linefeed(),
+            ([], b'E') => {
+                self.linefeed();
+                self.carriage_return();
+            }
+            ([], b'H') => {
+                let x = self.cursor.x;
+                self.tabs[x] = true;
+            }
+            ([], b'M') => self.reverse_index(),
+            ([], b'c') => self.reset(),
+            ([b'('], b'0') => self.charsets[0] = Charset::DecSpecial,
+            ([b'('], _) => self.charsets[0] = Charset::Ascii,
+            ([b')'], b'0') => self.charsets[1] = Charset::DecSpecial,
+            ([b')'], _) => self.charsets[1] = Charset::Ascii,
+            ([b'#'], b'8') => {
+                let pen = self.pen;
+                let grid = self.grid_mut();
+                for y in 0..grid.lines() {
+                    for cell in &mut grid.row_mut(y).cells {
+                        *cell = Cell::new('E', pen);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Undo the `%XX` escapes of a URL path.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(lines: usize, cols: usize, bytes: &[u8]) -> Terminal {
+        let mut terminal = Terminal::new(lines, cols);
+        terminal.advance(bytes);
+        terminal
+    }
+
+    fn cell(terminal: &Terminal, x: usize, y: usize) -> Cell {
+        terminal.screen().grid().row(y).cells[x]
+    }
+
+    fn at(x: usize, y: usize) -> Cursor {
+        Cursor { x, y }
+    }
+
+    #[test]
+    fn prints_and_wraps_at_the_margin() {
+        let terminal = terminal(4, 5, b"hello world\r\nok");
+        let screen = terminal.screen();
+        assert_eq!(screen.text(), "hello\n worl\nd\nok");
+        assert!(screen.grid().row(0).wrapped && screen.grid().row(1).wrapped);
+        assert!(!screen.grid().row(2).wrapped);
+        assert_eq!(screen.cursor(), at(2, 3));
+    }
+
+    #[test]
+    fn escape_sequences_split_over_reads_still_apply() {
+        let mut terminal = terminal(2, 10, b"\x1b[");
+        terminal.advance(b"2;4Hx");
+        assert_eq!(terminal.screen().text(), "\n   x");
+    }
+
+    #[test]
+    fn sgr_sets_and_resets_the_pen() {
+        let terminal = terminal(
+            1,
+            10,
+            b"\x1b[1;31ma\x1b[22;48;5;200mb\x1b[38:2::1:2:3mc\x1b[0md",
+        );
+        let a = cell(&terminal, 0, 0).style;
+        assert!(a.attrs.bold);
+        assert_eq!(a.fg, Color::Indexed(1));
+
+        let b = cell(&terminal, 1, 0).style;
+        assert!(!b.attrs.bold);
+        assert_eq!((b.fg, b.bg), (Color::Indexed(1), Color::Indexed(200)));
+        assert_eq!(cell(&terminal, 2, 0).style.fg, Color::Rgb(1, 2, 3));
+        assert_eq!(cell(&terminal, 3, 0).style, CellStyle::default());
+    }
+
+    #[test]
+    fn a_scroll_region_scrolls_on_its_own() {
+        let mut terminal = terminal(4, 10, b"top\r\n1\r\n2\r\nbottom");
+        // the region is lines 2 and 3, a line feed at its bottom scrolls
+        // only those
+        terminal.advance(b"\x1b[2;3r\x1b[3;1H\nnew");
+        assert_eq!(terminal.screen().text(), "top\n2\nnew\nbottom");
+        // none of it is history, only whole screen scrolls are
+        assert_eq!(terminal.screen().history_len(), 0);
+    }
+
+    #[test]
+    fn origin_mode_addresses_within_the_region() {
+        let terminal = terminal(5, 10, b"\x1b[2;4r\x1b[?6h\x1b[1;1Hx\x1b[9;1Hy");
+        let screen = terminal.screen();
+        assert_eq!(screen.text(), "\nx\n\ny\n");
+        assert_eq!(screen.cursor(), at(1, 3));
+    }
+
+    #[test]
+    fn the_alternate_screen_leaves_the_primary_one_alone() {
+        let mut terminal = terminal(2, 10, b"shell\x1b[?1049h");
+        assert!(terminal.screen().is_alternate());
+        assert_eq!(terminal.screen().text(), "\n");
+        terminal.advance(b"\x1b[2;3Hfull screen");
+        terminal.advance(b"\x1b[?1049l");
+        assert!(!terminal.screen().is_alternate());
+        assert_eq!(terminal.screen().text(), "shell\n");
+        assert_eq!(terminal.screen().cursor(), at(5, 0));
+    }
+
+    #[test]
+    fn tabs_stop_every_eight_columns_unless_set() {
+        let mut terminal = terminal(1, 30, b"\tx");
+        assert_eq!(terminal.screen().cursor(), at(9, 0));
+        // clear them all, set one at 3
+        terminal.advance(b"\r\x1b[3g\x1b[4G\x1bH\r\ty");
+        assert_eq!(terminal.screen().text(), "   y    x");
+        // with no stop left, a tab goes to the margin
+        terminal.advance(b"\t");
+        assert_eq!(terminal.screen().cursor(), at(29, 0));
+    }
+
+    #[test]
+    fn queries_are_answered() {
+        let mut terminal = terminal(5, 10, b"\x1b[5n\x1b[3;4H\x1b[6n\x1b[c");
+        assert_eq!(terminal.take_replies(), b"\x1b[0n\x1b[3;4R\x1b[?1;2c");
+        // in origin mode the position is within the region
+        terminal.advance(b"\x1b[2;4r\x1b[?6h\x1b[2;1H\x1b[6n");
+        assert_eq!(terminal.take_replies(), b"\x1b[2;1R");
+    }
+
+    #[test]
+    fn the_cursor_reported_above_the_region_is_on_its_first_line() {
+        // the cursor saved at the top of one region comes back above the
+        // next one down
+        let mut terminal = terminal(10, 10, b"\x1b[?6h\x1b[5;8r\x1b7\x1b[6;8r\x1b8");
+        assert_eq!(terminal.screen().cursor(), at(0, 4));
+        terminal.advance(b"\x1b[6n");
+        assert_eq!(terminal.take_replies(), b"\x1b[1;1R");
+    }
+
+    #[test]
+    fn scrollback_keeps_the_newest_lines_up_to_the_limit() {
+        let mut terminal = terminal(2, 10, b"");
+        terminal.set_history_limit(3);
+        terminal.advance(b"1\r\n2\r\n3\r\n4\r\n5\r\n6");
+        let screen = terminal.screen();
+        assert_eq!(screen.history_len(), 3);
+        let lines: Vec<String> = (0..screen.buffer_len())
+            .map(|i| screen.buffer_line(i).unwrap().text())
+            .collect();
+        assert_eq!(lines, ["2", "3", "4", "5", "6"]);
+
+        terminal.set_history_limit(1);
+        assert_eq!(terminal.screen().buffer_line(0).unwrap().text(), "4");
+    }
+
+    #[test]
+    fn the_view_stays_put_while_output_scrolls_under_it() {
+        let mut terminal = terminal(2, 10, b"1\r\n2\r\n3\r\n4");
+        terminal.scroll_display(2);
+        let shown = |terminal: &Terminal| -> Vec<String> {
+            terminal.screen().display_rows().map(Row::text).collect()
+        };
+        assert_eq!(shown(&terminal), ["1", "2"]);
+        assert_eq!(terminal.screen().display_cursor(), None);
+
+        terminal.advance(b"\r\n5");
+        assert_eq!(terminal.screen().display_offset(), 3);
+        assert_eq!(shown(&terminal), ["1", "2"]);
+
+        // it stops at the oldest line, and at the live screen
+        terminal.scroll_display(10);
+        assert_eq!(terminal.screen().display_offset(), 3);
+        terminal.scroll_display(-10);
+        assert_eq!(shown(&terminal), ["4", "5"]);
+    }
+
+    #[test]
+    fn a_trimmed_history_takes_the_view_with_it() {
+        let mut terminal = terminal(2, 10, b"1\r\n2\r\n3\r\n4");
+        terminal.set_history_limit(2);
+        terminal.scroll_display(2);
+        terminal.advance(b"\r\n5");
+        // the line the view was on is gone, it's at the oldest one left
+        assert_eq!(terminal.screen().display_offset(), 2);
+        assert_eq!(terminal.screen().buffer_line(0).unwrap().text(), "2");
+    }
+
+    #[test]
+    fn a_reset_keeps_the_history_limit() {
+        let mut terminal = terminal(2, 10, b"");
+        terminal.set_history_limit(5);
+        terminal.advance(b"old\x1bc");
+        assert_eq!(terminal.screen().text(), "\n");
+        assert_eq!(terminal.screen().history_limit, 5);
+    }
+}
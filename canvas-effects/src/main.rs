@@ -1,12 +1,20 @@
+use alacrema_templates::Templates;
 use anathema::backend::tui::Style;
 use anathema::component::*;
 use anathema::default_widgets::Canvas;
 use anathema::prelude::*;
 use rand::prelude::*;
 use std::cmp::max;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::time::Instant;
 
+/// The templates built into the binary, by file name.
+const TEMPLATES: &[(&str, &str)] = &[
+    ("ui.aml", include_str!("ui.aml")),
+    ("canvasFX.aml", include_str!("canvasFX.aml")),
+];
+
 const BUBBLE: &str = "·⋅◌⊙⊚⦾⁜";
 
 #[derive(State)]
@@ -171,6 +179,14 @@ impl Component for CanvasFX {
 }
 
 fn main() {
+    // overrides come from `$CANVAS_EFFECTS_TEMPLATES`, when it's set
+    let dir = std::env::var_os("CANVAS_EFFECTS_TEMPLATES").map(PathBuf::from);
+    let templates = Templates::new(TEMPLATES, dir);
+    if let Err(err) = templates.check(&[("main", "ui.aml"), ("canvasfx", "canvasFX.aml")]) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+
     let doc = Document::new("@main");
 
     let mut backend = {
@@ -186,12 +202,17 @@ fn main() {
 
     let mut builder = Runtime::builder(doc, &backend);
     builder
-        .component("main", "src/ui.aml", UIMain::new(), UIMainState::new())
+        .component(
+            "main",
+            templates.get("ui.aml").unwrap(),
+            UIMain::new(),
+            UIMainState::new(),
+        )
         .unwrap();
     builder
         .prototype(
             "canvasfx",
-            "src/canvasFX.aml",
+            templates.get("canvasFX.aml").unwrap(),
             CanvasFX::new,
             CanvasFXState::new,
         )
@@ -0,0 +1,379 @@
+use crate::command::UserRequestType;
+use crate::layout::Axis;
+use crate::loom::{ExitPolicy, PaneId};
+use anathema::component::{KeyCode, KeyEvent};
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+
+/// How far one key press moves a divider, as a share of the split.
+const RESIZE_STEP: f32 = 0.05;
+
+/// What the keys after the prefix do unless the config says otherwise.
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("n", "new-pane"),
+    ("%", "split-horizontal"),
+    ("\"", "split-vertical"),
+    ("H", "resize-pane left"),
+    ("L", "resize-pane right"),
+    ("K", "resize-pane up"),
+    ("J", "resize-pane down"),
+    ("x", "close-pane"),
+    ("R", "respawn-pane"),
+    ("T", "rename-pane"),
+    ("o", "next-pane"),
+    ("Left", "select-pane left"),
+    ("Right", "select-pane right"),
+    ("Up", "select-pane up"),
+    ("Down", "select-pane down"),
+    (";", "last-pane"),
+    ("Q", "display-panes"),
+    ("z", "zoom-pane"),
+    ("c", "new-window"),
+    ("&", "close-window"),
+    ("0", "select-window 0"),
+    ("1", "select-window 1"),
+    ("2", "select-window 2"),
+    ("3", "select-window 3"),
+    ("4", "select-window 4"),
+    ("5", "select-window 5"),
+    ("6", "select-window 6"),
+    ("7", "select-window 7"),
+    ("8", "select-window 8"),
+    ("9", "select-window 9"),
+    (")", "next-window"),
+    ("(", "previous-window"),
+    (">", "move-window right"),
+    ("<", "move-window left"),
+    (",", "rename-window"),
+    ("[", "copy-mode"),
+    ("PageUp", "copy-mode -u"),
+    ("]", "paste-buffer"),
+    ("d", "detach"),
+    ("q", "kill-session"),
+    ("j", "fps -1"),
+    ("k", "fps +1"),
+    ("r", "reload-config"),
+    ("~", "show-messages"),
+];
+
+/// A key as written in the config: a character, a key name like
+/// `PageUp`, either maybe with `C-` in front for control.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeySpec {
+    code: KeyCode,
+    ctrl: bool,
+}
+
+impl KeySpec {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let (ctrl, rest) = match s.strip_prefix("C-") {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, s),
+        };
+        let code = match rest {
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Tab" => KeyCode::Tab,
+            "BTab" => KeyCode::BackTab,
+            "Enter" => KeyCode::Enter,
+            "Escape" => KeyCode::Esc,
+            "Space" => KeyCode::Char(' '),
+            "BSpace" => KeyCode::Backspace,
+            "DC" => KeyCode::Delete,
+            "IC" => KeyCode::Insert,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next(), rest.strip_prefix('F')) {
+                    (Some(c), None, _) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+                    (Some(c), None, _) => KeyCode::Char(c),
+                    (_, _, Some(n)) if n.parse::<u8>().is_ok_and(|n| (1..=12).contains(&n)) => {
+                        KeyCode::F(n.parse().unwrap())
+                    }
+                    _ => return Err(anyhow!("unknown key {s:?}")),
+                }
+            }
+        };
+        Ok(Self { code, ctrl })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        match (self.code, key.code) {
+            // crossterm reports ctrl-c on its own
+            (KeyCode::Char('c'), KeyCode::CtrlC) => self.ctrl,
+            (KeyCode::Char(want), KeyCode::Char(got)) if self.ctrl => {
+                key.ctrl && want == got.to_ascii_lowercase()
+            }
+            (want, got) => !key.ctrl && want == got,
+        }
+    }
+}
+
+/// Which way `resize-pane` moves a divider, or `select-pane` the focus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Something a key can be bound to or a new session can start with,
+/// written the tmux way: a name, then maybe an argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    NewPane,
+    Split(Axis),
+    ResizePane(Direction),
+    ClosePane,
+    /// Without a title, a prompt asks for one.
+    RenamePane(Option<String>),
+    /// `respawn-pane -k` restarts a pane that's still running, too.
+    RespawnPane {
+        kill: bool,
+    },
+    /// What the focused pane does when its process exits.
+    OnExit(ExitPolicy),
+    NextPane,
+    /// The pane next to the focused one on that side.
+    SelectPane(Direction),
+    /// A pane by the number `display-panes` shows on it.
+    SelectPaneNumber(usize),
+    LastPane,
+    /// Show every pane's number on it for a moment, a digit picks one.
+    DisplayPanes,
+    /// Let the focused pane fill the window, or put it back.
+    ZoomPane,
+    NewWindow,
+    CloseWindow,
+    SelectWindow(usize),
+    /// `next-window` and `previous-window`.
+    CycleWindow(isize),
+    MoveWindow(isize),
+    /// Without a name, a prompt asks for one.
+    RenameWindow(Option<String>),
+    /// `copy-mode -u` starts a page up.
+    CopyMode {
+        page_up: bool,
+    },
+    Paste,
+    Detach,
+    KillSession,
+    /// Change the animation rate by this many frames per second.
+    Fps(i32),
+    ReloadConfig,
+    /// Open the log of every notice so far over the panes.
+    ShowMessages,
+}
+
+impl Command {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let (name, arg) = match s.trim().split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+        let direction = |arg: Option<&str>| match arg {
+            Some("left") => Ok(Direction::Left),
+            Some("right") => Ok(Direction::Right),
+            Some("up") => Ok(Direction::Up),
+            Some("down") => Ok(Direction::Down),
+            _ => Err(anyhow!("{name} needs left, right, up or down")),
+        };
+        let command = match (name, arg) {
+            ("new-pane", None) => Self::NewPane,
+            ("split-horizontal", None) => Self::Split(Axis::Horizontal),
+            ("split-vertical", None) => Self::Split(Axis::Vertical),
+            ("resize-pane", arg) => Self::ResizePane(direction(arg)?),
+            ("close-pane", None) => Self::ClosePane,
+            ("rename-pane", title) => Self::RenamePane(title.map(str::to_string)),
+            ("respawn-pane", None) => Self::RespawnPane { kill: false },
+            ("respawn-pane", Some("-k")) => Self::RespawnPane { kill: true },
+            ("on-exit", Some(policy)) => {
+                Self::OnExit(ExitPolicy::parse(policy).map_err(|err| anyhow!("on-exit: {err}"))?)
+            }
+            ("next-pane", None) => Self::NextPane,
+            ("select-pane", Some(number)) if number.parse::<usize>().is_ok() => {
+                Self::SelectPaneNumber(number.parse().unwrap())
+            }
+            ("select-pane", arg) => Self::SelectPane(direction(arg).map_err(|_| {
+                anyhow!("select-pane needs left, right, up, down or a pane number")
+            })?),
+            ("last-pane", None) => Self::LastPane,
+            ("display-panes", None) => Self::DisplayPanes,
+            ("zoom-pane", None) => Self::ZoomPane,
+            ("new-window", None) => Self::NewWindow,
+            ("close-window", None) => Self::CloseWindow,
+            ("select-window", Some(index)) => Self::SelectWindow(
+                index
+                    .parse()
+                    .map_err(|_| anyhow!("select-window needs a window number"))?,
+            ),
+            ("next-window", None) => Self::CycleWindow(1),
+            ("previous-window", None) => Self::CycleWindow(-1),
+            ("move-window", arg) => match direction(arg)? {
+                Direction::Left => Self::MoveWindow(-1),
+                Direction::Right => Self::MoveWindow(1),
+                _ => return Err(anyhow!("move-window needs left or right")),
+            },
+            ("rename-window", name) => Self::RenameWindow(name.map(str::to_string)),
+            ("copy-mode", None) => Self::CopyMode { page_up: false },
+            ("copy-mode", Some("-u")) => Self::CopyMode { page_up: true },
+            ("paste-buffer", None) => Self::Paste,
+            ("detach", None) => Self::Detach,
+            ("kill-session", None) => Self::KillSession,
+            ("fps", Some(delta)) => Self::Fps(
+                delta
+                    .parse()
+                    .map_err(|_| anyhow!("fps needs a change like +1 or -1"))?,
+            ),
+            ("reload-config", None) => Self::ReloadConfig,
+            ("show-messages", None) => Self::ShowMessages,
+            (_, Some(arg)) if KNOWN.contains(&name) => {
+                return Err(anyhow!("{name} doesn't take {arg:?}"));
+            }
+            (_, None) if KNOWN.contains(&name) => {
+                return Err(anyhow!("{name} needs an argument"));
+            }
+            _ => return Err(anyhow!("unknown command {s:?}")),
+        };
+        Ok(command)
+    }
+
+    /// What the server is asked to do for this command, given which pane
+    /// has focus and which window is on screen. `None` for commands only
+    /// the client can carry out.
+    pub fn request(&self, focused: Option<PaneId>, window: usize) -> Option<UserRequestType> {
+        let request = match self {
+            Self::NewPane => UserRequestType::NewPane,
+            Self::Split(axis) => UserRequestType::Split(*axis),
+            Self::ResizePane(direction) => {
+                let (axis, delta) = match direction {
+                    Direction::Left => (Axis::Horizontal, -RESIZE_STEP),
+                    Direction::Right => (Axis::Horizontal, RESIZE_STEP),
+                    Direction::Up => (Axis::Vertical, -RESIZE_STEP),
+                    Direction::Down => (Axis::Vertical, RESIZE_STEP),
+                };
+                UserRequestType::ResizeSplit {
+                    pane: focused?,
+                    axis,
+                    delta,
+                }
+            }
+            Self::ClosePane => UserRequestType::ClosePane(focused?),
+            Self::RenamePane(Some(title)) => UserRequestType::Rename {
+                pane: focused?,
+                title: title.clone(),
+            },
+            Self::RespawnPane { kill } => UserRequestType::Respawn {
+                pane: focused?,
+                kill: *kill,
+            },
+            Self::OnExit(policy) => UserRequestType::SetExitPolicy {
+                pane: focused?,
+                policy: *policy,
+            },
+            Self::LastPane => UserRequestType::LastPane,
+            Self::ZoomPane => UserRequestType::Zoom(focused?),
+            Self::NewWindow => UserRequestType::NewWindow,
+            Self::CloseWindow => UserRequestType::CloseWindow(window),
+            Self::SelectWindow(index) => UserRequestType::SelectWindow(*index),
+            Self::CycleWindow(offset) => UserRequestType::CycleWindow(*offset),
+            Self::MoveWindow(offset) => UserRequestType::MoveWindow(*offset),
+            Self::RenameWindow(Some(name)) => UserRequestType::RenameWindow {
+                window,
+                name: name.clone(),
+            },
+            Self::CopyMode { page_up: false } => UserRequestType::CopyMode {
+                pane: focused?,
+                on: true,
+            },
+            Self::Paste => UserRequestType::Paste(focused?),
+            Self::KillSession => UserRequestType::Quit,
+            Self::ReloadConfig => UserRequestType::ReloadConfig,
+            Self::NextPane
+            | Self::SelectPane(_)
+            | Self::SelectPaneNumber(_)
+            | Self::DisplayPanes
+            | Self::RenamePane(None)
+            | Self::RenameWindow(None)
+            | Self::CopyMode { page_up: true }
+            | Self::Detach
+            | Self::Fps(_)
+            | Self::ShowMessages => return None,
+        };
+        Some(request)
+    }
+
+    /// Whether the server can run this on its own, as it has to for the
+    /// commands a session starts with.
+    pub fn runs_in_server(&self) -> bool {
+        // a pane to act on can always be stood in for
+        self.request(Some(PaneId::from_raw(0)), 0).is_some()
+    }
+}
+
+/// Every command name, for telling a bad argument from a bad name.
+const KNOWN: &[&str] = &[
+    "new-pane",
+    "split-horizontal",
+    "split-vertical",
+    "resize-pane",
+    "close-pane",
+    "rename-pane",
+    "respawn-pane",
+    "on-exit",
+    "next-pane",
+    "select-pane",
+    "last-pane",
+    "display-panes",
+    "zoom-pane",
+    "new-window",
+    "close-window",
+    "select-window",
+    "next-window",
+    "previous-window",
+    "move-window",
+    "rename-window",
+    "copy-mode",
+    "paste-buffer",
+    "detach",
+    "kill-session",
+    "fps",
+    "reload-config",
+    "show-messages",
+];
+
+/// What every key after the prefix is bound to.
+#[derive(Debug, Clone)]
+pub struct Bindings(Vec<(KeySpec, Command)>);
+
+impl Bindings {
+    /// The default bindings with `overrides` from the config on top,
+    /// where binding a key to `none` takes it away.
+    pub fn new(overrides: &BTreeMap<String, String>) -> anyhow::Result<Self> {
+        let mut bindings = vec![];
+        let defaults = DEFAULT_BINDINGS.iter().map(|(k, c)| (*k, *c));
+        let overrides = overrides.iter().map(|(k, c)| (k.as_str(), c.as_str()));
+        for (key, command) in defaults.chain(overrides) {
+            let spec = KeySpec::parse(key)?;
+            bindings.retain(|(bound, _)| *bound != spec);
+            if command != "none" {
+                let command =
+                    Command::parse(command).map_err(|err| anyhow!("key {key:?}: {err}"))?;
+                bindings.push((spec, command));
+            }
+        }
+        Ok(Self(bindings))
+    }
+
+    pub fn get(&self, key: &KeyEvent) -> Option<&Command> {
+        self.0
+            .iter()
+            .find_map(|(spec, command)| spec.matches(key).then_some(command))
+    }
+}
@@ -0,0 +1,72 @@
+use crate::layout::Rect;
+use crate::loom::PaneId;
+
+/// A cell between panes, and the piece of line drawn in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    pub x: u16,
+    pub y: u16,
+    pub c: char,
+    /// Next to the focused pane, whose borders have colours of their own.
+    pub active: bool,
+}
+
+/// The lines along the dividers when `panes` cover `rows` x `cols`: every
+/// cell no pane covers is a divider, and joins up with the ones around it.
+pub fn lines(
+    panes: &[(PaneId, Rect)],
+    focused: Option<PaneId>,
+    (rows, cols): (u16, u16),
+) -> Vec<Line> {
+    if panes.is_empty() {
+        return vec![];
+    }
+    let (rows, cols) = (rows as usize, cols as usize);
+    let mut free = vec![true; rows * cols];
+    for (_, rect) in panes {
+        for y in (rect.y as usize..(rect.y + rect.height) as usize).take_while(|y| *y < rows) {
+            for x in (rect.x as usize..(rect.x + rect.width) as usize).take_while(|x| *x < cols) {
+                free[y * cols + x] = false;
+            }
+        }
+    }
+    let is_free = |x: usize, y: usize| x < cols && y < rows && free[y * cols + x];
+    let around = panes
+        .iter()
+        .find(|(id, _)| Some(*id) == focused)
+        .map(|(_, rect)| *rect);
+
+    let mut lines = vec![];
+    for y in 0..rows {
+        for x in 0..cols {
+            if !is_free(x, y) {
+                continue;
+            }
+            let up = y > 0 && is_free(x, y - 1);
+            let down = is_free(x, y + 1);
+            let left = x > 0 && is_free(x - 1, y);
+            let right = is_free(x + 1, y);
+            let c = match (up, down, left, right) {
+                (true, true, true, true) => '┼',
+                (true, true, false, true) => '├',
+                (true, true, true, false) => '┤',
+                (false, true, true, true) => '┬',
+                (true, false, true, true) => '┴',
+                (false, true, false, true) => '┌',
+                (false, true, true, false) => '┐',
+                (true, false, false, true) => '└',
+                (true, false, true, false) => '┘',
+                (_, _, false, false) => '│',
+                (false, false, _, _) => '─',
+            };
+            let (x, y) = (x as u16, y as u16);
+            // the cells around the focused pane, corners included
+            let active = around.is_some_and(|rect| {
+                (rect.x.saturating_sub(1)..=rect.x + rect.width).contains(&x)
+                    && (rect.y.saturating_sub(1)..=rect.y + rect.height).contains(&y)
+            });
+            lines.push(Line { x, y, c, active });
+        }
+    }
+    lines
+}
@@ -0,0 +1,71 @@
+use anyhow::anyhow;
+
+pub const USAGE: &str = "\
+usage: fizzle [command]
+
+  new [-s name]            start a session and attach to it (the default)
+  attach [-t name]         attach to a running session
+  ls                       list running sessions
+  kill-session [-t name]   end a session and everything running in it
+
+without -t, the most recently started session is used.
+
+settings are read from $FIZZLE_CONFIG, or else
+~/.config/fizzle/config.toml, when it exists. .aml files put in
+~/.config/fizzle/templates (or the config's `templates`) replace the
+ones built in.
+";
+
+/// What fizzle was asked to do on the command line.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    New(Option<String>),
+    Attach(Option<String>),
+    List,
+    Kill(Option<String>),
+    /// Run a session's server in the foreground, how `new` starts one.
+    Server(String),
+    Help,
+}
+
+impl Action {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter();
+        let Some(command) = args.next() else {
+            return Ok(Self::New(None));
+        };
+        let rest: Vec<String> = args.collect();
+        match command.as_str() {
+            "new" | "new-session" => Ok(Self::New(flag(&rest, "-s")?)),
+            "attach" | "attach-session" | "a" => Ok(Self::Attach(flag(&rest, "-t")?)),
+            "ls" | "list-sessions" => {
+                no_args(&rest)?;
+                Ok(Self::List)
+            }
+            "kill-session" => Ok(Self::Kill(flag(&rest, "-t")?)),
+            "server" => match rest.as_slice() {
+                [name] => Ok(Self::Server(name.clone())),
+                _ => Err(anyhow!("server needs exactly one session name")),
+            },
+            "-h" | "--help" | "help" => Ok(Self::Help),
+            other => Err(anyhow!("unknown command {other:?}\n\n{USAGE}")),
+        }
+    }
+}
+
+/// The value of an optional `flag value` pair, the only thing a command
+/// takes.
+fn flag(args: &[String], name: &str) -> anyhow::Result<Option<String>> {
+    match args {
+        [] => Ok(None),
+        [flag, value] if flag == name => Ok(Some(value.clone())),
+        _ => Err(anyhow!("expected at most {name} <name>, got {args:?}")),
+    }
+}
+
+fn no_args(args: &[String]) -> anyhow::Result<()> {
+    match args {
+        [] => Ok(()),
+        _ => Err(anyhow!("unexpected arguments {args:?}")),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::protocol::{self, ClientMessage, ServerMessage};
+use anyhow::anyhow;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// A client's end of the socket to a session server. Messages from the
+/// server are read on a thread of their own and picked up with `try_recv`.
+pub struct Connection {
+    stream: UnixStream,
+    incoming: Receiver<ServerMessage>,
+}
+
+impl Connection {
+    pub fn open(name: &str) -> anyhow::Result<Self> {
+        let path = protocol::socket_path(name)?;
+        let stream = UnixStream::connect(&path)
+            .map_err(|err| anyhow!("can't reach session {name}: {err}"))?;
+
+        let (tx, incoming) = mpsc::channel();
+        let mut reader = stream.try_clone()?;
+        std::thread::spawn(move || {
+            while let Ok(Some(message)) = protocol::read(&mut reader) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+            // the server went away without saying goodbye
+            _ = tx.send(ServerMessage::Exit);
+        });
+
+        Ok(Self { stream, incoming })
+    }
+
+    /// A connection to a server played by a test, which gets the other
+    /// end of the socket and a way to hand the client messages directly.
+    /// Those are there for the next `try_recv`, no reader thread between.
+    #[cfg(test)]
+    pub fn pair() -> anyhow::Result<(Self, UnixStream, mpsc::Sender<ServerMessage>)> {
+        let (stream, server) = UnixStream::pair()?;
+        let (tx, incoming) = mpsc::channel();
+        Ok((Self { stream, incoming }, server, tx))
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> std::io::Result<()> {
+        self.stream.write_all(&protocol::encode(message))
+    }
+
+    /// The next message from the server, if one came in.
+    pub fn try_recv(&self) -> Option<ServerMessage> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(ServerMessage::Exit),
+        }
+    }
+
+    /// Wait for the next message from the server.
+    pub fn recv(&self) -> ServerMessage {
+        self.incoming.recv().unwrap_or(ServerMessage::Exit)
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+/// The time as the ui goes by: prefix timeouts, how long notices stay up,
+/// the statusline's clock and the animations. Tests move it along by hand
+/// with [`advance`] instead of waiting.
+#[cfg(not(test))]
+pub fn now() -> Instant {
+    Instant::now()
+}
+
+/// Seconds since the epoch, to tell when the clock has moved on without
+/// asking for the local time.
+#[cfg(not(test))]
+pub fn current_second() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[cfg(test)]
+thread_local! {
+    static START: Instant = Instant::now();
+    static PASSED: std::cell::Cell<Duration> = const { std::cell::Cell::new(Duration::ZERO) };
+}
+
+/// Where [`current_second`] starts in tests, a Tuesday afternoon in 2023.
+#[cfg(test)]
+pub const TEST_EPOCH: u64 = 1_700_000_000;
+
+#[cfg(test)]
+pub fn now() -> Instant {
+    START.with(|start| *start) + PASSED.get()
+}
+
+#[cfg(test)]
+pub fn current_second() -> u64 {
+    TEST_EPOCH + PASSED.get().as_secs()
+}
+
+/// Move this thread's clock forward.
+#[cfg(test)]
+pub fn advance(by: Duration) {
+    PASSED.set(PASSED.get() + by);
+}
+
+/// How long ago `then` was.
+pub fn since(then: Instant) -> Duration {
+    now().saturating_duration_since(then)
+}
@@ -0,0 +1,191 @@
+use crate::copy::CopyAction;
+use crate::layout::Axis;
+use crate::loom::{ExitPolicy, PaneId};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How many queued commands are handled per tick, so a burst of requests
+/// can't stall rendering.
+pub const COMMANDS_PER_TICK: usize = 32;
+
+/// Everything the UI can ask of the multiplexer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UserRequestType {
+    /// A new pane next to the focused one, split along its longer side.
+    NewPane,
+    Split(Axis),
+    ClosePane(PaneId),
+    /// Move the divider next to `pane` so it grows by `delta` of the split.
+    ResizeSplit {
+        pane: PaneId,
+        axis: Axis,
+        delta: f32,
+    },
+    /// Drag the divider right of `pane`, or below it, to column or row
+    /// `to` of the window.
+    MoveDivider {
+        pane: PaneId,
+        axis: Axis,
+        to: u16,
+    },
+    Focus(PaneId),
+    /// Focus the pane that had the focus before the focused one.
+    LastPane,
+    /// Let a pane fill its window, or put it back in its split if it
+    /// does already.
+    Zoom(PaneId),
+    SendKeys {
+        pane: PaneId,
+        bytes: Vec<u8>,
+    },
+    /// Give a pane a title of its own, or its command's back if `title`
+    /// is empty.
+    Rename {
+        pane: PaneId,
+        title: String,
+    },
+    /// Start a pane's command over, killing it first if it still runs
+    /// and `kill` says so.
+    Respawn {
+        pane: PaneId,
+        kill: bool,
+    },
+    /// What a pane does when its process exits.
+    SetExitPolicy {
+        pane: PaneId,
+        policy: ExitPolicy,
+    },
+    /// Enter or leave copy mode, where the pane shows its scrollback and
+    /// text can be selected.
+    CopyMode {
+        pane: PaneId,
+        on: bool,
+    },
+    /// Move a pane in copy mode this many lines back, or forward if
+    /// negative.
+    Scroll {
+        pane: PaneId,
+        lines: isize,
+    },
+    Copy {
+        pane: PaneId,
+        action: CopyAction,
+    },
+    /// Type the paste buffer into a pane.
+    Paste(PaneId),
+    /// A new window with one pane in it, placed after the active one.
+    NewWindow,
+    SelectWindow(usize),
+    /// Select the window this many places to the right, wrapping around.
+    CycleWindow(isize),
+    /// Move the active window this many places along the tab bar.
+    MoveWindow(isize),
+    RenameWindow {
+        window: usize,
+        name: String,
+    },
+    /// Close a window along with all of its panes.
+    CloseWindow(usize),
+    /// Read the config again, for panes spawned from now on.
+    ReloadConfig,
+    Quit,
+}
+
+/// What a successfully handled command did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutcome {
+    Spawned(PaneId),
+    Closed(PaneId),
+    Resized(PaneId),
+    Focused(PaneId),
+    /// Whether the pane fills its window now.
+    Zoomed(PaneId, bool),
+    Sent(PaneId),
+    Renamed(PaneId),
+    Respawned(PaneId),
+    ExitPolicySet(PaneId, ExitPolicy),
+    Scrolled(PaneId),
+    /// Text was yanked into the paste buffer.
+    Copied(PaneId, String),
+    Pasted(PaneId),
+    WindowOpened(usize),
+    WindowSelected(usize),
+    WindowMoved(usize),
+    WindowRenamed(usize),
+    WindowClosed(usize),
+    Reloaded,
+    Quit,
+}
+
+impl CommandOutcome {
+    /// Whether it's not worth a notice. Keys are sent and panes resized
+    /// all the time, and a reload is told about by the session itself.
+    pub fn is_routine(&self) -> bool {
+        matches!(
+            self,
+            Self::Resized(_)
+                | Self::Focused(_)
+                | Self::Zoomed(..)
+                | Self::Sent(_)
+                | Self::Scrolled(_)
+                | Self::Reloaded
+        )
+    }
+}
+
+impl fmt::Display for CommandOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawned(id) => write!(f, "opened pane {id}"),
+            Self::Closed(id) => write!(f, "closed pane {id}"),
+            Self::Resized(id) => write!(f, "resized pane {id}"),
+            Self::Focused(id) => write!(f, "focused pane {id}"),
+            Self::Zoomed(id, true) => write!(f, "zoomed pane {id}"),
+            Self::Zoomed(id, false) => write!(f, "unzoomed pane {id}"),
+            Self::Sent(id) => write!(f, "sent keys to pane {id}"),
+            Self::Renamed(id) => write!(f, "renamed pane {id}"),
+            Self::Respawned(id) => write!(f, "respawned pane {id}"),
+            Self::ExitPolicySet(id, policy) => match policy {
+                ExitPolicy::Keep => write!(f, "pane {id} stays open when it exits"),
+                ExitPolicy::Close => write!(f, "pane {id} closes when it exits"),
+                ExitPolicy::Respawn => write!(f, "pane {id} respawns when it exits"),
+            },
+            Self::Scrolled(id) => write!(f, "scrolled pane {id}"),
+            Self::Copied(id, text) => {
+                write!(
+                    f,
+                    "copied {} characters from pane {id}",
+                    text.chars().count()
+                )
+            }
+            Self::Pasted(id) => write!(f, "pasted into pane {id}"),
+            Self::WindowOpened(index) => write!(f, "opened window {index}"),
+            Self::WindowSelected(index) => write!(f, "switched to window {index}"),
+            Self::WindowMoved(index) => write!(f, "moved window to {index}"),
+            Self::WindowRenamed(index) => write!(f, "renamed window {index}"),
+            Self::WindowClosed(index) => write!(f, "closed window {index}"),
+            Self::Reloaded => write!(f, "reloaded the config"),
+            Self::Quit => write!(f, "quitting"),
+        }
+    }
+}
+
+/// First in, first out queue of user requests, drained in batches
+/// by `UIMain::on_tick`.
+#[derive(Default)]
+pub struct CommandQueue {
+    vd: VecDeque<UserRequestType>,
+}
+
+impl CommandQueue {
+    pub fn push(&mut self, cmd: UserRequestType) {
+        self.vd.push_back(cmd);
+    }
+
+    /// Take up to `max` commands, oldest first.
+    pub fn drain_batch(&mut self, max: usize) -> Vec<UserRequestType> {
+        let n = max.min(self.vd.len());
+        self.vd.drain(..n).collect()
+    }
+}
@@ -0,0 +1,474 @@
+use crate::binding::{Bindings, Command};
+use crate::copy::ModeKeys;
+use crate::loom::ExitPolicy;
+use crate::prefix::PrefixKey;
+use crate::status::{Format, Segment};
+use alacrema_term::DEFAULT_HISTORY_LIMIT;
+use anathema::component::Color;
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The most scrollback a pane can be given, about a few hundred megabytes
+/// of it on a wide terminal.
+const MAX_HISTORY: usize = 1_000_000;
+
+/// Everything fizzle lets you change, as read from `config.toml`. Every
+/// setting has a default, the file only needs what's different.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub keys: Keys,
+    /// What panes run instead of `$SHELL`, program first.
+    pub command: Option<Vec<String>>,
+    /// Set for everything started in a pane.
+    pub env: BTreeMap<String, String>,
+    /// Lines of scrollback kept per pane, up to a million.
+    pub history: usize,
+    /// What a pane does once its process exits: `keep` it on screen,
+    /// `close` it or `respawn` the command.
+    pub on_exit: ExitPolicy,
+    /// Whether fizzle takes the mouse: clicks focus panes and pick tabs,
+    /// dividers are dragged and the wheel scrolls back. Programs that ask
+    /// for the mouse still get it. Read when a client attaches.
+    pub mouse: bool,
+    /// Draw the panes that don't have the focus faint.
+    pub dim_inactive: bool,
+    pub fps: i32,
+    /// How far the `fps` command can take the frame rate.
+    pub max_fps: i32,
+    /// How big panes are made before a client says how much room it has.
+    pub default_size: Size,
+    pub colors: Colors,
+    /// How long a notice stays in the statusfeed, 0 to keep it until the
+    /// next one.
+    pub notice_seconds: u64,
+    /// What the statusline shows after the tabs, one segment after the
+    /// other.
+    pub segments: Vec<SegmentConfig>,
+    /// Commands a new session runs, to start it with some panes.
+    pub startup: Vec<String>,
+    /// Where `.aml` files are looked for before the ones built in, see
+    /// [`templates_dir`].
+    pub templates: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Keys {
+    pub prefix: String,
+    /// `vi` or `emacs`, for copy mode.
+    pub mode_keys: String,
+    /// Keys after the prefix and the commands they run, on top of the
+    /// defaults.
+    pub bindings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Size {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorPair {
+    pub fg: String,
+    pub bg: String,
+}
+
+impl ColorPair {
+    fn new(fg: &str, bg: &str) -> Self {
+        Self {
+            fg: fg.to_string(),
+            bg: bg.to_string(),
+        }
+    }
+}
+
+/// A statusline segment as written in the config. Colours left out are
+/// the statusline's.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SegmentConfig {
+    /// See [`Format::parse`].
+    pub format: String,
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+}
+
+impl SegmentConfig {
+    fn new(format: &str, colors: Option<(&str, &str)>) -> Self {
+        Self {
+            format: format.to_string(),
+            fg: colors.map(|(fg, _)| fg.to_string()),
+            bg: colors.map(|(_, bg)| bg.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Colors {
+    pub statusline: ColorPair,
+    pub statusfeed: ColorPair,
+    pub prefix: ColorPair,
+    pub prefix_armed: ColorPair,
+    pub tab: ColorPair,
+    pub tab_active: ColorPair,
+    /// A window that printed or rang the bell while out of sight.
+    pub tab_alert: ColorPair,
+    /// The scroll position shown in copy mode.
+    pub copy_mode: ColorPair,
+    /// The lines between panes, and the numbers `display-panes` shows.
+    pub pane_border: ColorPair,
+    /// The same for the focused pane.
+    pub pane_active_border: ColorPair,
+    /// Notices in the statusfeed, by how much they matter.
+    pub notice_info: ColorPair,
+    pub notice_warn: ColorPair,
+    pub notice_error: ColorPair,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            keys: Keys::default(),
+            command: None,
+            env: BTreeMap::new(),
+            history: DEFAULT_HISTORY_LIMIT,
+            on_exit: ExitPolicy::default(),
+            mouse: true,
+            dim_inactive: false,
+            fps: 24,
+            max_fps: 30,
+            default_size: Size { rows: 24, cols: 80 },
+            colors: Colors::default(),
+            notice_seconds: 5,
+            segments: vec![
+                SegmentConfig::new("{?exit  {exit} }", Some(("white", "red"))),
+                SegmentConfig::new("{?status  exit {status} }", Some(("black", "yellow"))),
+                SegmentConfig::new(" {command}:{cwd:24} ", None),
+                SegmentConfig::new("> [{time}] {session} //", None),
+            ],
+            startup: vec![],
+            templates: None,
+        }
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self {
+            prefix: "C-a".to_string(),
+            mode_keys: "vi".to_string(),
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            statusline: ColorPair::new("white", "red"),
+            statusfeed: ColorPair::new("white", "blue"),
+            prefix: ColorPair::new("white", "red"),
+            prefix_armed: ColorPair::new("black", "yellow"),
+            tab: ColorPair::new("white", "red"),
+            tab_active: ColorPair::new("red", "white"),
+            tab_alert: ColorPair::new("yellow", "red"),
+            copy_mode: ColorPair::new("black", "yellow"),
+            pane_border: ColorPair::new("grey", "reset"),
+            pane_active_border: ColorPair::new("red", "reset"),
+            notice_info: ColorPair::new("white", "blue"),
+            notice_warn: ColorPair::new("black", "yellow"),
+            notice_error: ColorPair::new("white", "red"),
+        }
+    }
+}
+
+/// The config with everything in it checked and parsed.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub config: Config,
+    pub prefix: PrefixKey,
+    pub mode_keys: ModeKeys,
+    pub bindings: Bindings,
+    pub segments: Vec<Segment>,
+    pub startup: Vec<Command>,
+}
+
+/// Where the config is looked for: `$FIZZLE_CONFIG`, or `fizzle/config.toml`
+/// in `$XDG_CONFIG_HOME` or `~/.config`.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("FIZZLE_CONFIG") {
+        return Some(path.into());
+    }
+    Some(dir()?.join("config.toml"))
+}
+
+/// `fizzle` in `$XDG_CONFIG_HOME` or `~/.config`.
+fn dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("fizzle"))
+}
+
+/// Where templates that change how fizzle looks are kept: the config's
+/// `templates`, or else `templates` in fizzle's config directory.
+pub fn templates_dir(config: &Config) -> Option<PathBuf> {
+    config
+        .templates
+        .clone()
+        .or_else(|| Some(dir()?.join("templates")))
+}
+
+/// Read and check the config. Without a file everything is left at its
+/// default.
+pub fn load() -> anyhow::Result<Settings> {
+    load_from(path())
+}
+
+fn load_from(path: Option<PathBuf>) -> anyhow::Result<Settings> {
+    let config = match &path {
+        Some(path) if path.exists() => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("can't read {}", path.display()))?;
+            toml::from_str(&text).map_err(|err| anyhow!("{}: {err}", path.display()))?
+        }
+        _ => Config::default(),
+    };
+
+    let where_ = path.map_or("config".to_string(), |p| p.display().to_string());
+    check(config).map_err(|err| anyhow!("{where_}: {err}"))
+}
+
+/// Turn a config into settings, or say what's wrong with it.
+pub fn check(config: Config) -> anyhow::Result<Settings> {
+    let prefix =
+        PrefixKey::parse(&config.keys.prefix).map_err(|err| anyhow!("keys.prefix: {err}"))?;
+    let mode_keys =
+        ModeKeys::parse(&config.keys.mode_keys).map_err(|err| anyhow!("keys.mode-keys: {err}"))?;
+    let bindings =
+        Bindings::new(&config.keys.bindings).map_err(|err| anyhow!("keys.bindings: {err}"))?;
+
+    if config.command.as_ref().is_some_and(|argv| argv.is_empty()) {
+        return Err(anyhow!("command: needs at least the program to run"));
+    }
+    if config.fps < 1 || config.max_fps < config.fps {
+        return Err(anyhow!(
+            "fps: {} should be at least 1 and no more than max-fps ({})",
+            config.fps,
+            config.max_fps
+        ));
+    }
+    if config.history > MAX_HISTORY {
+        return Err(anyhow!(
+            "history: {} is more than the {MAX_HISTORY} lines panes can keep",
+            config.history
+        ));
+    }
+    if config.default_size.rows == 0 || config.default_size.cols == 0 {
+        return Err(anyhow!("default-size: rows and cols can't be 0"));
+    }
+
+    let colors = &config.colors;
+    for (name, pair) in [
+        ("statusline", &colors.statusline),
+        ("statusfeed", &colors.statusfeed),
+        ("prefix", &colors.prefix),
+        ("prefix-armed", &colors.prefix_armed),
+        ("tab", &colors.tab),
+        ("tab-active", &colors.tab_active),
+        ("tab-alert", &colors.tab_alert),
+        ("copy-mode", &colors.copy_mode),
+        ("pane-border", &colors.pane_border),
+        ("pane-active-border", &colors.pane_active_border),
+        ("notice-info", &colors.notice_info),
+        ("notice-warn", &colors.notice_warn),
+        ("notice-error", &colors.notice_error),
+    ] {
+        for color in [&pair.fg, &pair.bg] {
+            check_color(color).map_err(|err| anyhow!("colors.{name}: {err}"))?;
+        }
+    }
+
+    let mut segments = vec![];
+    for (i, segment) in config.segments.iter().enumerate() {
+        let format =
+            Format::parse(&segment.format).map_err(|err| anyhow!("segments[{i}]: {err}"))?;
+        let fg = segment.fg.clone().unwrap_or(colors.statusline.fg.clone());
+        let bg = segment.bg.clone().unwrap_or(colors.statusline.bg.clone());
+        for color in [&fg, &bg] {
+            check_color(color).map_err(|err| anyhow!("segments[{i}]: {err}"))?;
+        }
+        segments.push(Segment { format, fg, bg });
+    }
+
+    let mut startup = vec![];
+    for (i, line) in config.startup.iter().enumerate() {
+        let command = Command::parse(line).map_err(|err| anyhow!("startup[{i}]: {err}"))?;
+        if !command.runs_in_server() {
+            return Err(anyhow!("startup[{i}]: {line:?} can't run at startup"));
+        }
+        startup.push(command);
+    }
+
+    Ok(Settings {
+        config,
+        prefix,
+        mode_keys,
+        bindings,
+        segments,
+        startup,
+    })
+}
+
+fn check_color(color: &str) -> anyhow::Result<()> {
+    match color.parse::<Color>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(anyhow!(
+            "{color:?} isn't a colour, try a name like \"red\", a number up to 255 or \"#rrggbb\""
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(toml: &str) -> String {
+        let config: Config = toml::from_str(toml).unwrap();
+        check(config).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn history_is_kept_within_bounds() {
+        let config: Config = toml::from_str("history = 1000000").unwrap();
+        assert_eq!(check(config).unwrap().config.history, MAX_HISTORY);
+        assert_eq!(
+            error("history = 1000001"),
+            "history: 1000001 is more than the 1000000 lines panes can keep"
+        );
+        assert_eq!(
+            error("history = 18446744073709551615"),
+            "history: 18446744073709551615 is more than the 1000000 lines panes can keep"
+        );
+    }
+
+    #[test]
+    fn mistakes_are_pointed_out_by_key() {
+        let cases = [
+            (
+                "[keys]\nprefix = \"C-ab\"",
+                "keys.prefix: bad prefix key \"C-ab\"",
+            ),
+            (
+                "[keys]\nmode-keys = \"nano\"",
+                "keys.mode-keys: bad mode keys \"nano\"",
+            ),
+            (
+                "[keys.bindings]\nC-Nope = \"new-pane\"",
+                "keys.bindings: unknown key \"C-Nope\"",
+            ),
+            (
+                "[keys.bindings]\nm = \"fly\"",
+                "keys.bindings: key \"m\": unknown command \"fly\"",
+            ),
+            (
+                "[keys.bindings]\nm = \"select-window x\"",
+                "keys.bindings: key \"m\": select-window needs",
+            ),
+            ("command = []", "command: needs at least the program to run"),
+            (
+                "fps = 40",
+                "fps: 40 should be at least 1 and no more than max-fps (30)",
+            ),
+            (
+                "default-size = { rows = 0, cols = 80 }",
+                "default-size: rows and cols can't be 0",
+            ),
+            (
+                "[colors]\ntab = { fg = \"mauve\", bg = \"red\" }",
+                "colors.tab: \"mauve\" isn't a colour",
+            ),
+            (
+                "[[segments]]\nformat = \"{nope}\"",
+                "segments[0]: unknown variable \"nope\"",
+            ),
+            (
+                "[[segments]]\nformat = \"{session\"",
+                "segments[0]: {session is missing its closing }",
+            ),
+            (
+                "[[segments]]\nformat = \"x\"\nbg = \"#12345\"",
+                "segments[0]: \"#12345\" isn't a colour",
+            ),
+            (
+                "startup = [\"new-pane\", \"detach\"]",
+                "startup[1]: \"detach\" can't run at startup",
+            ),
+            (
+                "startup = [\"split\"]",
+                "startup[0]: unknown command \"split\"",
+            ),
+        ];
+        for (toml, expected) in cases {
+            let err = error(toml);
+            assert!(err.starts_with(expected), "{toml:?} gave {err:?}");
+        }
+    }
+
+    #[test]
+    fn what_is_fine_is_taken_up() {
+        let settings = check(
+            toml::from_str(
+                r##"
+                on-exit = "keep"
+                [keys]
+                prefix = "C-b"
+                mode-keys = "emacs"
+                bindings = { m = "split-vertical", x = "none" }
+                [colors]
+                tab = { fg = "#ff8800", bg = "236" }
+                [[segments]]
+                format = "{{{session}}}"
+                "##,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(settings.config.on_exit, ExitPolicy::Keep);
+        assert_eq!(settings.mode_keys, ModeKeys::Emacs);
+        assert_eq!(settings.segments.len(), 1);
+        assert_eq!(settings.segments[0].fg, "white");
+    }
+
+    #[test]
+    fn the_file_is_named_in_errors() {
+        let path = std::env::temp_dir().join(format!("fizzle-config-{}.toml", std::process::id()));
+        let cases = [
+            ("colour = \"red\"", "unknown field `colour`"),
+            ("fps = \"fast\"", "invalid type"),
+            (
+                "[keys]\nmode-keys = \"nano\"",
+                "keys.mode-keys: bad mode keys",
+            ),
+        ];
+        for (toml, expected) in cases {
+            std::fs::write(&path, toml).unwrap();
+            let err = load_from(Some(path.clone())).unwrap_err().to_string();
+            let prefix = format!("{}: ", path.display());
+            assert!(err.starts_with(&prefix), "{err}");
+            assert!(err.contains(expected), "{toml:?} gave {err:?}");
+        }
+        _ = std::fs::remove_file(&path);
+        // without a file it's all defaults
+        assert!(load_from(Some(path)).is_ok());
+        assert!(load_from(None).is_ok());
+    }
+}
@@ -0,0 +1,685 @@
+use crate::search::{Query, Search, SearchStatus};
+use alacrema_term::{Color, Cursor, Screen, Terminal};
+use anathema::component::{KeyCode, KeyEvent};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+/// Which key table copy mode uses, like tmux's `mode-keys`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ModeKeys {
+    #[default]
+    Vi,
+    Emacs,
+}
+
+impl ModeKeys {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "vi" => Ok(Self::Vi),
+            "emacs" => Ok(Self::Emacs),
+            _ => Err(anyhow!("bad mode keys {s:?}, expected vi or emacs")),
+        }
+    }
+
+    /// What `key` does in copy mode, if anything.
+    pub fn action(self, key: &KeyEvent) -> Option<CopyAction> {
+        use CopyAction::*;
+        use Motion::*;
+        // the arrows and paging keys are the same either way
+        let common = match key.code {
+            KeyCode::Left => Some(Move(Left)),
+            KeyCode::Right => Some(Move(Right)),
+            KeyCode::Up => Some(Move(Up)),
+            KeyCode::Down => Some(Move(Down)),
+            KeyCode::Home => Some(Move(LineStart)),
+            KeyCode::End => Some(Move(LineEnd)),
+            KeyCode::PageUp => Some(Move(PageUp)),
+            KeyCode::PageDown => Some(Move(PageDown)),
+            _ => None,
+        };
+        if common.is_some() {
+            return common;
+        }
+
+        let action = match self {
+            Self::Vi => match key.code {
+                KeyCode::Char('h') if !key.ctrl => Move(Left),
+                KeyCode::Char('l') => Move(Right),
+                KeyCode::Char('k') => Move(Up),
+                KeyCode::Char('j') => Move(Down),
+                KeyCode::Char('0') | KeyCode::Char('^') => Move(LineStart),
+                KeyCode::Char('$') => Move(LineEnd),
+                KeyCode::Char('w') => Move(WordForward),
+                KeyCode::Char('b') if key.ctrl => Move(PageUp),
+                KeyCode::Char('b') => Move(WordBack),
+                KeyCode::Char('e') => Move(WordEnd),
+                KeyCode::Char('f') if key.ctrl => Move(PageDown),
+                KeyCode::Char('u') if key.ctrl => Move(HalfPageUp),
+                KeyCode::Char('d') if key.ctrl => Move(HalfPageDown),
+                KeyCode::Char('g') => Move(Top),
+                KeyCode::Char('G') => Move(Bottom),
+                KeyCode::Char('v') if key.ctrl => Select(Selection::Block),
+                KeyCode::Char('v') | KeyCode::Char(' ') => Select(Selection::Char),
+                KeyCode::Char('V') => Select(Selection::Line),
+                KeyCode::Char('y') | KeyCode::Enter => Yank,
+                KeyCode::Char('n') => SearchAgain { reverse: false },
+                KeyCode::Char('N') => SearchAgain { reverse: true },
+                KeyCode::Esc => Cancel,
+                KeyCode::Char('q') | KeyCode::CtrlC => Exit,
+                _ => return None,
+            },
+            // the backend drops Alt, so the Meta bindings arrive as the
+            // bare letter, which emacs has no other use for here
+            Self::Emacs => match key.code {
+                KeyCode::Char('b') if key.ctrl => Move(Left),
+                KeyCode::Char('f') if key.ctrl => Move(Right),
+                KeyCode::Char('p') if key.ctrl => Move(Up),
+                KeyCode::Char('n') if key.ctrl => Move(Down),
+                KeyCode::Char('a') if key.ctrl => Move(LineStart),
+                KeyCode::Char('e') if key.ctrl => Move(LineEnd),
+                KeyCode::Char('v') if key.ctrl => Move(PageDown),
+                KeyCode::Char(' ') | KeyCode::Char('@') if key.ctrl => Select(Selection::Char),
+                KeyCode::Null => Select(Selection::Char),
+                KeyCode::Char('w') if key.ctrl => Yank,
+                KeyCode::Char('g') if key.ctrl => Cancel,
+                KeyCode::Char('f') => Move(WordForward),
+                KeyCode::Char('b') => Move(WordBack),
+                KeyCode::Char('v') => Move(PageUp),
+                KeyCode::Char('<') => Move(Top),
+                KeyCode::Char('>') => Move(Bottom),
+                KeyCode::Char('R') => Select(Selection::Block),
+                KeyCode::Char('w') => Yank,
+                KeyCode::Char('n') => SearchAgain { reverse: false },
+                KeyCode::Char('N') => SearchAgain { reverse: true },
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::CtrlC => Exit,
+                _ => return None,
+            },
+        };
+        Some(action)
+    }
+
+    /// Whether `key` starts a search, and which way and how it looks if
+    /// so: `(backward, regex)`. Typing the query happens in a prompt.
+    pub fn search(self, key: &KeyEvent) -> Option<(bool, bool)> {
+        match (self, key.code) {
+            (Self::Vi, KeyCode::Char('/')) => Some((false, true)),
+            (Self::Vi, KeyCode::Char('?')) => Some((true, true)),
+            (Self::Emacs, KeyCode::Char('s')) if key.ctrl => Some((false, false)),
+            (Self::Emacs, KeyCode::Char('r')) if key.ctrl => Some((true, false)),
+            _ => None,
+        }
+    }
+}
+
+/// Which way the copy mode cursor goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    LineStart,
+    /// The last character on the line that isn't blank.
+    LineEnd,
+    WordForward,
+    WordBack,
+    WordEnd,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    /// The oldest line of the scrollback.
+    Top,
+    /// The last line of the screen.
+    Bottom,
+}
+
+/// How much a selection takes between its two ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Selection {
+    /// Everything in reading order, like selecting with a mouse.
+    Char,
+    /// Whole lines.
+    Line,
+    /// The rectangle with the two ends at opposite corners.
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CopyAction {
+    Move(Motion),
+    /// Start selecting at the cursor, or change what kind of selection
+    /// it is. Asking for the kind already in use drops the selection.
+    Select(Selection),
+    /// Copy the selection, or the cursor's line without one, and leave.
+    Yank,
+    /// Drop the selection, or leave if there is none.
+    Cancel,
+    Exit,
+    /// Jump to the query's first match from where the cursor was when
+    /// the query was started, sent again for every edit to it.
+    Search(Query),
+    /// The query is typed in. Unless it's kept, the cursor goes back to
+    /// where it started and the search is dropped.
+    SearchDone {
+        keep: bool,
+    },
+    /// The next match of the last search, or the one the other way.
+    SearchAgain {
+        reverse: bool,
+    },
+}
+
+/// What became of copy mode after an action.
+pub enum CopyOutcome {
+    Stay,
+    Exit,
+    Yanked(String),
+}
+
+/// A position in a pane's buffer, scrollback and screen together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Point {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Point {
+    /// Where the point is once `lines` dropped off the front of the
+    /// scrollback, the first line if its own went with them.
+    fn after_dropping(self, lines: usize) -> Self {
+        match self.line.checked_sub(lines) {
+            Some(line) => Self { line, ..self },
+            None => Self { line: 0, col: 0 },
+        }
+    }
+}
+
+/// A cursor over a pane's scrollback, with a selection hanging off it.
+#[derive(Debug)]
+pub struct CopyMode {
+    cursor: Point,
+    // the end of the selection that stays put, and how it selects
+    anchor: Option<(Point, Selection)>,
+    search: Option<Search>,
+    // where the cursor was before the query being typed moved it
+    search_origin: Option<Point>,
+    // the screen's count of dropped scrollback lines the points go by
+    dropped: u64,
+}
+
+impl CopyMode {
+    /// Start where the terminal's own cursor is on screen.
+    pub fn new(screen: &Screen) -> Self {
+        let cursor = screen.cursor();
+        Self {
+            cursor: Point {
+                line: screen.history_len() + cursor.y,
+                col: cursor.x,
+            },
+            anchor: None,
+            search: None,
+            search_origin: None,
+            dropped: screen.history_dropped(),
+        }
+    }
+
+    /// How many lines dropped off the front of the scrollback since the
+    /// points were last moved for it.
+    fn behind(&self, screen: &Screen) -> usize {
+        (screen.history_dropped() - self.dropped) as usize
+    }
+
+    /// Keep the points on the text they were on while output pushes old
+    /// lines out of a full scrollback.
+    fn catch_up(&mut self, screen: &Screen) {
+        let lines = self.behind(screen);
+        if lines == 0 {
+            return;
+        }
+        self.cursor = self.cursor.after_dropping(lines);
+        if let Some((anchor, _)) = self.anchor.as_mut() {
+            *anchor = anchor.after_dropping(lines);
+        }
+        if let Some(origin) = self.search_origin.as_mut() {
+            *origin = origin.after_dropping(lines);
+        }
+        self.dropped = screen.history_dropped();
+    }
+
+    pub fn search_status(&self) -> Option<&SearchStatus> {
+        self.search.as_ref().map(|search| search.status())
+    }
+
+    pub fn apply(&mut self, action: CopyAction, terminal: &mut Terminal) -> CopyOutcome {
+        self.catch_up(terminal.screen());
+        match action {
+            CopyAction::Move(motion) => {
+                self.motion(motion, terminal);
+                CopyOutcome::Stay
+            }
+            CopyAction::Select(kind) => {
+                self.anchor = match self.anchor {
+                    Some((_, current)) if current == kind => None,
+                    Some((anchor, _)) => Some((anchor, kind)),
+                    None => Some((self.cursor, kind)),
+                };
+                CopyOutcome::Stay
+            }
+            CopyAction::Yank => {
+                let (anchor, kind) = self.anchor.unwrap_or((self.cursor, Selection::Line));
+                CopyOutcome::Yanked(selected_text(terminal.screen(), anchor, self.cursor, kind))
+            }
+            CopyAction::Cancel if self.anchor.is_some() => {
+                self.anchor = None;
+                CopyOutcome::Stay
+            }
+            CopyAction::Cancel | CopyAction::Exit => CopyOutcome::Exit,
+            CopyAction::Search(query) => {
+                let origin = *self.search_origin.get_or_insert(self.cursor);
+                let search = Search::new(query);
+                let screen = terminal.screen();
+                let backward = search.query().backward;
+                self.cursor = search.find(screen, origin, backward).unwrap_or(origin);
+                self.search = Some(search);
+                self.found(terminal);
+                CopyOutcome::Stay
+            }
+            CopyAction::SearchDone { keep } => {
+                let origin = self.search_origin.take();
+                if !keep {
+                    self.search = None;
+                    self.cursor = origin.unwrap_or(self.cursor);
+                    self.follow(terminal);
+                }
+                CopyOutcome::Stay
+            }
+            CopyAction::SearchAgain { reverse } => {
+                if let Some(search) = &self.search {
+                    let backward = search.query().backward != reverse;
+                    if let Some(found) = search.find(terminal.screen(), self.cursor, backward) {
+                        self.cursor = found;
+                    }
+                    self.found(terminal);
+                }
+                CopyOutcome::Stay
+            }
+        }
+    }
+
+    /// Bring the cursor into view after a search moved it, and count the
+    /// matches around it.
+    fn found(&mut self, terminal: &mut Terminal) {
+        self.follow(terminal);
+        if let Some(search) = self.search.as_mut() {
+            search.update_status(terminal.screen(), self.cursor);
+        }
+    }
+
+    /// The view was scrolled by `lines`, the cursor keeps its place on
+    /// screen like it would with a mouse wheel.
+    pub fn scrolled(&mut self, screen: &Screen, lines: isize) {
+        self.catch_up(screen);
+        self.cursor.line = self.cursor.line.saturating_add_signed(-lines);
+    }
+
+    fn motion(&mut self, motion: Motion, terminal: &mut Terminal) {
+        let screen = terminal.screen();
+        let last_line = screen.buffer_len() - 1;
+        let last_col = screen.cols() - 1;
+        let page = screen.lines().saturating_sub(1).max(1);
+        let Point { line, col } = self.cursor;
+        self.cursor = match motion {
+            Motion::Left => Point {
+                line,
+                col: col.saturating_sub(1),
+            },
+            Motion::Right => Point {
+                line,
+                col: (col + 1).min(last_col),
+            },
+            Motion::Up => Point {
+                line: line.saturating_sub(1),
+                col,
+            },
+            Motion::Down => Point {
+                line: (line + 1).min(last_line),
+                col,
+            },
+            Motion::LineStart => Point { line, col: 0 },
+            Motion::LineEnd => Point {
+                line,
+                col: line_end(screen, line),
+            },
+            Motion::PageUp => Point {
+                line: line.saturating_sub(page),
+                col,
+            },
+            Motion::PageDown => Point {
+                line: (line + page).min(last_line),
+                col,
+            },
+            Motion::HalfPageUp => Point {
+                line: line.saturating_sub(page / 2),
+                col,
+            },
+            Motion::HalfPageDown => Point {
+                line: (line + page / 2).min(last_line),
+                col,
+            },
+            Motion::Top => Point { line: 0, col: 0 },
+            Motion::Bottom => Point {
+                line: last_line,
+                col: 0,
+            },
+            Motion::WordForward => word_forward(screen, self.cursor),
+            Motion::WordBack => word_back(screen, self.cursor),
+            Motion::WordEnd => word_end(screen, self.cursor),
+        };
+        self.follow(terminal);
+    }
+
+    /// Scroll the view just enough to have the cursor in it.
+    fn follow(&self, terminal: &mut Terminal) {
+        let screen = terminal.screen();
+        let top = screen.display_top();
+        let bottom = top + screen.lines() - 1;
+        if self.cursor.line < top {
+            terminal.scroll_display((top - self.cursor.line) as isize);
+        } else if self.cursor.line > bottom {
+            terminal.scroll_display(-((self.cursor.line - bottom) as isize));
+        }
+    }
+
+    /// The view of the pane, with the copy cursor, the selection and
+    /// search matches on it.
+    pub fn dump(&self, screen: &Screen) -> Vec<u8> {
+        let top = screen.display_top();
+        let behind = self.behind(screen);
+        let own = self.cursor.after_dropping(behind);
+        let anchor = self
+            .anchor
+            .map(|(anchor, kind)| (anchor.after_dropping(behind), kind));
+        let cursor = own
+            .line
+            .checked_sub(top)
+            .filter(|y| *y < screen.lines())
+            .map(|y| Cursor { x: own.col, y });
+        let matches: Vec<Vec<(usize, usize)>> = match &self.search {
+            Some(search) => (top..top + screen.lines())
+                .map(|line| search.matches_on(screen, line))
+                .collect(),
+            None => vec![],
+        };
+        screen.dump_styled(cursor, |at, style| {
+            let point = Point {
+                line: top + at.y,
+                col: at.x,
+            };
+            let found = matches.get(at.y).and_then(|line| {
+                line.iter()
+                    .find(|(start, end)| (*start..*end).contains(&at.x))
+            });
+            if let Some((start, _)) = found {
+                // the match the cursor is on stands out from the rest
+                let current = own.line == point.line && own.col == *start;
+                style.fg = Color::Indexed(0);
+                style.bg = Color::Indexed(if current { 5 } else { 6 });
+            }
+            if anchor.is_some_and(|(anchor, kind)| selected(anchor, own, kind, point)) {
+                style.attrs.reverse = !style.attrs.reverse;
+            }
+        })
+    }
+}
+
+fn cell_char(screen: &Screen, point: Point) -> char {
+    screen
+        .buffer_line(point.line)
+        .and_then(|row| row.cells.get(point.col))
+        .map_or(' ', |cell| cell.c)
+}
+
+fn line_end(screen: &Screen, line: usize) -> usize {
+    let Some(row) = screen.buffer_line(line) else {
+        return 0;
+    };
+    row.cells
+        .iter()
+        .take(screen.cols())
+        .rposition(|cell| cell.c != ' ')
+        .unwrap_or(0)
+}
+
+/// Every point of the buffer after `from`, in reading order.
+fn points_after(screen: &Screen, from: Point) -> impl Iterator<Item = Point> {
+    let cols = screen.cols();
+    let end = screen.buffer_len() * cols;
+    (from.line * cols + from.col + 1..end).map(move |i| Point {
+        line: i / cols,
+        col: i % cols,
+    })
+}
+
+/// Every point of the buffer before `from`, nearest first.
+fn points_before(screen: &Screen, from: Point) -> impl Iterator<Item = Point> {
+    let cols = screen.cols();
+    (0..from.line * cols + from.col).rev().map(move |i| Point {
+        line: i / cols,
+        col: i % cols,
+    })
+}
+
+fn is_word(c: char) -> bool {
+    !c.is_whitespace()
+}
+
+/// The start of the next word, vi's `w`.
+fn word_forward(screen: &Screen, from: Point) -> Point {
+    let mut in_word = is_word(cell_char(screen, from));
+    for point in points_after(screen, from) {
+        let word = is_word(cell_char(screen, point)) && point.col != 0 || {
+            // a new line always ends the word before it
+            in_word = false;
+            is_word(cell_char(screen, point))
+        };
+        if word && !in_word {
+            return point;
+        }
+        in_word = word;
+    }
+    from
+}
+
+/// The start of this word, or the one before if already there, vi's `b`.
+fn word_back(screen: &Screen, from: Point) -> Point {
+    let mut found = None;
+    for point in points_before(screen, from) {
+        let word = is_word(cell_char(screen, point));
+        match (word, found) {
+            (true, _) => found = Some(point),
+            (false, Some(start)) => return start,
+            (false, None) => {}
+        }
+        if point.col == 0 && found.is_some() {
+            return point;
+        }
+    }
+    found.unwrap_or(from)
+}
+
+/// The end of this word, or the next if already there, vi's `e`.
+fn word_end(screen: &Screen, from: Point) -> Point {
+    let mut found = None;
+    for point in points_after(screen, from) {
+        let word = is_word(cell_char(screen, point));
+        match (word, found) {
+            (true, _) if point.col == 0 && found.is_some() => return found.unwrap(),
+            (true, _) => found = Some(point),
+            (false, Some(end)) => return end,
+            (false, None) => {}
+        }
+    }
+    found.unwrap_or(from)
+}
+
+/// Whether `point` is part of the selection from `anchor` to `cursor`.
+fn selected(anchor: Point, cursor: Point, kind: Selection, point: Point) -> bool {
+    let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+    if point.line < start.line || point.line > end.line {
+        return false;
+    }
+    match kind {
+        Selection::Char => start <= point && point <= end,
+        Selection::Line => true,
+        Selection::Block => {
+            let (left, right) = (anchor.col.min(cursor.col), anchor.col.max(cursor.col));
+            (left..=right).contains(&point.col)
+        }
+    }
+}
+
+/// The text the selection covers. Lines that wrapped on screen come out
+/// joined back up, trailing blanks are left out.
+fn selected_text(screen: &Screen, anchor: Point, cursor: Point, kind: Selection) -> String {
+    let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+    let mut text = String::new();
+    for line in start.line..=end.line {
+        let Some(row) = screen.buffer_line(line) else {
+            break;
+        };
+        let mut piece = String::new();
+        let cols = (0..screen.cols())
+            .filter(|col| selected(anchor, cursor, kind, Point { line, col: *col }));
+        for col in cols {
+            match row.cells.get(col) {
+                Some(cell) => piece.extend(cell.chars()),
+                None => piece.push(' '),
+            }
+        }
+        let joined = row.wrapped && kind != Selection::Block;
+        if joined {
+            text.push_str(&piece);
+        } else {
+            text.push_str(piece.trim_end());
+            if line != end.line {
+                text.push('\n');
+            }
+        }
+    }
+    if kind == Selection::Line {
+        text.push('\n');
+    }
+    text
+}
+
+/// The OSC 52 sequence that sets the clipboard to `text`.
+pub fn osc52(text: &str) -> Vec<u8> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = b"\x1b]52;c;".to_vec();
+    for chunk in text.as_bytes().chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize]);
+            } else {
+                out.push(b'=');
+            }
+        }
+    }
+    out.push(b'\x07');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(rows: usize, cols: usize, text: &str) -> Terminal {
+        let mut terminal = Terminal::new(rows, cols);
+        terminal.advance(text.as_bytes());
+        terminal
+    }
+
+    fn at(line: usize, col: usize) -> Point {
+        Point { line, col }
+    }
+
+    #[test]
+    fn the_clipboard_gets_base64_padded_out() {
+        let osc52 = |text| String::from_utf8(osc52(text)).unwrap();
+        assert_eq!(osc52(""), "\x1b]52;c;\x07");
+        assert_eq!(osc52("h"), "\x1b]52;c;aA==\x07");
+        assert_eq!(osc52("he"), "\x1b]52;c;aGU=\x07");
+        assert_eq!(osc52("hel"), "\x1b]52;c;aGVs\x07");
+        assert_eq!(osc52("hello"), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(osc52("é"), "\x1b]52;c;w6k=\x07");
+    }
+
+    #[test]
+    fn selections_take_what_they_cover() {
+        // the first line wraps onto the second
+        let terminal = terminal(3, 6, "abcdefgh\r\nxy   z");
+        let screen = terminal.screen();
+        let text = |anchor, cursor, kind| selected_text(screen, anchor, cursor, kind);
+        assert_eq!(text(at(0, 2), at(1, 1), Selection::Char), "cdefgh");
+        assert_eq!(text(at(2, 1), at(0, 4), Selection::Char), "efgh\nxy");
+        assert_eq!(text(at(0, 1), at(2, 2), Selection::Block), "bc\nh\ny");
+        assert_eq!(text(at(1, 3), at(2, 0), Selection::Line), "gh\nxy   z\n");
+    }
+
+    #[test]
+    fn words_end_at_the_end_of_a_line() {
+        let terminal = terminal(3, 10, "foo bar\r\n  baz qux\r\nend");
+        let screen = terminal.screen();
+        assert_eq!(word_forward(screen, at(0, 0)), at(0, 4));
+        assert_eq!(word_forward(screen, at(0, 4)), at(1, 2));
+        assert_eq!(word_forward(screen, at(1, 6)), at(2, 0));
+        assert_eq!(word_back(screen, at(1, 2)), at(0, 4));
+        assert_eq!(word_back(screen, at(1, 3)), at(1, 2));
+        assert_eq!(word_back(screen, at(2, 0)), at(1, 6));
+        assert_eq!(word_end(screen, at(0, 0)), at(0, 2));
+        assert_eq!(word_end(screen, at(0, 2)), at(0, 6));
+        assert_eq!(word_end(screen, at(1, 6)), at(1, 8));
+        assert_eq!(word_end(screen, at(1, 8)), at(2, 2));
+        // nowhere further to go
+        assert_eq!(word_forward(screen, at(2, 1)), at(2, 1));
+        assert_eq!(word_back(screen, at(0, 0)), at(0, 0));
+    }
+
+    #[test]
+    fn a_word_running_to_the_edge_isnt_joined_to_the_next_line() {
+        let terminal = terminal(2, 3, "foo\r\nbar");
+        let screen = terminal.screen();
+        assert_eq!(word_forward(screen, at(0, 0)), at(1, 0));
+        assert_eq!(word_end(screen, at(0, 0)), at(0, 2));
+        assert_eq!(word_end(screen, at(0, 2)), at(1, 2));
+        assert_eq!(word_back(screen, at(1, 0)), at(0, 0));
+        assert_eq!(word_back(screen, at(1, 2)), at(1, 0));
+    }
+
+    #[test]
+    fn the_selection_stays_on_its_text_as_the_scrollback_fills() {
+        let mut terminal = Terminal::new(2, 10);
+        terminal.set_history_limit(3);
+        terminal.advance(b"a\r\nb\r\nc\r\nd");
+        let mut copy = CopyMode::new(terminal.screen());
+        for action in [
+            CopyAction::Move(Motion::Up),
+            CopyAction::Move(Motion::Up),
+            CopyAction::Select(Selection::Line),
+            CopyAction::Move(Motion::Down),
+        ] {
+            copy.apply(action, &mut terminal);
+        }
+        // a build keeps printing, and `a` goes to make room
+        terminal.advance(b"\r\ne\r\nf");
+        assert_eq!(terminal.screen().history_dropped(), 1);
+        let CopyOutcome::Yanked(text) = copy.apply(CopyAction::Yank, &mut terminal) else {
+            panic!("nothing yanked");
+        };
+        assert_eq!(text, "b\nc\n");
+    }
+}
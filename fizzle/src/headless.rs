@@ -0,0 +1,350 @@
+use crate::client::Connection;
+use crate::clock;
+use crate::config::{self, Config};
+use crate::protocol::{self, ClientMessage, ServerMessage};
+use alacrema_templates::Templates;
+use anathema::backend::Backend;
+use anathema::component::{Event, KeyCode, KeyEvent, KeyState, MouseEvent, MouseState};
+use anathema::geometry::{Pos, Size};
+use anathema::prelude::*;
+use anathema::resolver::{AttributeStorage, Attributes};
+use anathema::runtime::{Error, Frame};
+use anathema::widgets::paint::{Glyph, paint};
+use anathema::widgets::tabindex::TabIndex;
+use anathema::widgets::{GlyphMap, PaintChildren, Style, WidgetRenderer};
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a tick is, as far as the clock is concerned.
+pub const TICK: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    glyph: Glyph,
+    style: Style,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        glyph: Glyph::Single(' ', 1),
+        style: Style::new(),
+    };
+}
+
+/// What widgets paint into, the way the tui backend's buffer takes it.
+struct Grid {
+    size: Size,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    fn new(size: Size) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::BLANK; size.width as usize * size.height as usize],
+        }
+    }
+
+    fn cell_mut(&mut self, pos: Pos) -> Option<&mut Cell> {
+        let (x, y) = (u16::try_from(pos.x).ok()?, u16::try_from(pos.y).ok()?);
+        if x >= self.size.width || y >= self.size.height {
+            return None;
+        }
+        self.cells
+            .get_mut(y as usize * self.size.width as usize + x as usize)
+    }
+}
+
+impl WidgetRenderer for Grid {
+    fn draw_glyph(&mut self, glyph: Glyph, pos: Pos) {
+        if let Some(cell) = self.cell_mut(pos) {
+            cell.glyph = glyph;
+        }
+    }
+
+    fn set_attributes(&mut self, attribs: &Attributes<'_>, pos: Pos) {
+        self.set_style(Style::from_cell_attribs(attribs), pos);
+    }
+
+    fn set_style(&mut self, style: Style, pos: Pos) {
+        if let Some(cell) = self.cell_mut(pos) {
+            cell.style.fg = style.fg.or(cell.style.fg);
+            cell.style.bg = style.bg.or(cell.style.bg);
+            cell.style.attributes |= style.attributes;
+        }
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+/// A backend with no terminal behind it: what's painted goes into a grid
+/// of cells to look at, and events come from a queue the test fills.
+pub struct HeadlessBackend {
+    painted: Grid,
+    // what the last render showed, clusters looked up, and nothing in the
+    // cells wide characters cover
+    shown: Vec<(String, Style)>,
+    events: VecDeque<Event>,
+}
+
+impl HeadlessBackend {
+    pub fn new(rows: u16, cols: u16) -> Self {
+        let size = Size::new(cols, rows);
+        Self {
+            painted: Grid::new(size),
+            shown: vec![(" ".to_string(), Style::new()); rows as usize * cols as usize],
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    fn width(&self) -> usize {
+        self.painted.size.width as usize
+    }
+
+    /// Row `y` as text, without the spaces at the end.
+    pub fn line(&self, y: usize) -> String {
+        let width = self.width();
+        let row = &self.shown[y * width..(y + 1) * width];
+        let line: String = row.iter().map(|(text, _)| text.as_str()).collect();
+        line.trim_end().to_string()
+    }
+
+    /// Every row, one per line.
+    pub fn text(&self) -> String {
+        let rows = self.painted.size.height as usize;
+        let lines: Vec<String> = (0..rows).map(|y| self.line(y)).collect();
+        lines.join("\n")
+    }
+
+    pub fn style_at(&self, x: usize, y: usize) -> Style {
+        self.shown[y * self.width() + x].1
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn size(&self) -> Size {
+        self.painted.size
+    }
+
+    fn next_event(&mut self, _timeout: Duration) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn resize(&mut self, new_size: Size, _glyph_map: &mut GlyphMap) {
+        self.painted = Grid::new(new_size);
+        self.shown = vec![(" ".to_string(), Style::new()); self.painted.cells.len()];
+    }
+
+    fn paint<'bp>(
+        &mut self,
+        glyph_map: &mut GlyphMap,
+        widgets: PaintChildren<'_, 'bp>,
+        attribute_storage: &AttributeStorage<'bp>,
+    ) {
+        paint(&mut self.painted, glyph_map, widgets, attribute_storage);
+    }
+
+    fn render(&mut self, glyph_map: &mut GlyphMap) {
+        let width = self.width();
+        let mut covered = 0;
+        for (x, (shown, cell)) in self.shown.iter_mut().zip(&self.painted.cells).enumerate() {
+            if x % width == 0 {
+                covered = 0;
+            }
+            if x % width < covered {
+                *shown = (String::new(), cell.style);
+                continue;
+            }
+            let (text, glyph_width) = match cell.glyph {
+                Glyph::Single(c, w) => (c.to_string(), w),
+                Glyph::Cluster(index, w) => (glyph_map.get(index).cloned().unwrap_or_default(), w),
+            };
+            covered = x % width + glyph_width.max(1) as usize;
+            *shown = (text, cell.style);
+        }
+    }
+
+    // fixed and floating widgets are painted one after the other, so the
+    // grid starts over once the frame is shown rather than on paint
+    fn clear(&mut self) {
+        self.painted.cells.fill(Cell::BLANK);
+    }
+}
+
+/// The server's end of a client's connection, played by the test.
+pub struct FakeServer {
+    stream: UnixStream,
+    tx: Sender<ServerMessage>,
+}
+
+impl FakeServer {
+    /// Hand the client a message, which it takes in on its next tick.
+    pub fn send(&self, message: ServerMessage) {
+        self.tx.send(message).unwrap();
+    }
+
+    /// Everything the client sent so far that wasn't taken yet.
+    pub fn received(&mut self) -> Vec<ClientMessage> {
+        let mut messages = vec![];
+        loop {
+            match protocol::read(&mut self.stream) {
+                Ok(Some(message)) => messages.push(message),
+                Ok(None) => break,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => panic!("reading from the client: {err}"),
+            }
+        }
+        messages
+    }
+}
+
+/// One client's ui, as a test drives it: keys in, ticks forward, and the
+/// screen to look at after.
+pub struct Ui<'a, 'rt, 'bp> {
+    pub backend: &'a mut HeadlessBackend,
+    pub server: &'a mut FakeServer,
+    frame: &'a mut Frame<'rt, 'bp, ()>,
+    farewell: Arc<Mutex<String>>,
+    stopped: bool,
+}
+
+impl Ui<'_, '_, '_> {
+    /// Run one frame, with the clock moved on by a tick first. Nothing
+    /// happens once the ui stopped.
+    pub fn tick(&mut self) {
+        if self.stopped {
+            return;
+        }
+        clock::advance(TICK);
+        match self.frame.tick(self.backend) {
+            Ok(_) => {}
+            Err(Error::Stop) => self.stopped = true,
+            Err(err) => panic!("{err}"),
+        }
+        self.frame.present(self.backend);
+        self.frame.cleanup();
+    }
+
+    pub fn ticks(&mut self, count: usize) {
+        for _ in 0..count {
+            self.tick();
+        }
+    }
+
+    /// Let `duration` go by, a tick at a time.
+    pub fn wait(&mut self, duration: Duration) {
+        self.ticks(duration.div_duration_f64(TICK).ceil() as usize);
+    }
+
+    /// Press a key and let the ui take it in.
+    pub fn press(&mut self, code: KeyCode, ctrl: bool) {
+        self.backend.push_event(Event::Key(KeyEvent {
+            code,
+            ctrl,
+            state: KeyState::Press,
+        }));
+        // one frame takes the key, the next shows what it did
+        self.ticks(2);
+    }
+
+    /// Do something with the mouse at screen column `x`, row `y`.
+    pub fn mouse(&mut self, x: u16, y: u16, state: MouseState) {
+        self.backend
+            .push_event(Event::Mouse(MouseEvent { x, y, state }));
+        self.ticks(2);
+    }
+
+    /// Type each character of `text`, `\r` as Enter.
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\r' => self.press(KeyCode::Enter, false),
+                c => self.press(KeyCode::Char(c), false),
+            }
+        }
+    }
+
+    /// Press `key` with the default prefix, `C-a`, before it.
+    pub fn command(&mut self, key: char) {
+        self.press(KeyCode::Char('a'), true);
+        self.press(KeyCode::Char(key), false);
+    }
+
+    /// Give the ui a message from the server and a frame to take it in.
+    pub fn receive(&mut self, message: ServerMessage) {
+        self.server.send(message);
+        self.ticks(2);
+    }
+
+    pub fn line(&self, y: usize) -> String {
+        self.backend.line(y)
+    }
+
+    pub fn text(&self) -> String {
+        self.backend.text()
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Why the ui stopped, like `detached`.
+    pub fn farewell(&self) -> String {
+        self.farewell.lock().unwrap().clone()
+    }
+}
+
+/// Run the ui on a `rows` x `cols` headless backend with `config`, and
+/// let `test` drive it. The ui has had a first frame by then, and is
+/// attached to a server that the test plays.
+pub fn run(rows: u16, cols: u16, config: Config, test: impl FnOnce(&mut Ui<'_, '_, '_>)) {
+    let settings = config::check(config).unwrap();
+    let templates = Templates::new(crate::TEMPLATES, None);
+    let (connection, stream, tx) = Connection::pair().unwrap();
+    stream.set_nonblocking(true).unwrap();
+    let mut server = FakeServer { stream, tx };
+
+    let mut backend = HeadlessBackend::new(rows, cols);
+    let farewell = Arc::new(Mutex::new(String::new()));
+    let mut builder = Runtime::builder(Document::new("@main"), &backend);
+    crate::add_components(
+        &mut builder,
+        connection,
+        "test",
+        (settings, templates),
+        farewell.clone(),
+    )
+    .unwrap();
+
+    let mut test = Some(test);
+    builder
+        .finish(&mut backend, |runtime, backend| {
+            runtime.with_frame(backend, |backend, mut frame| {
+                // the first frame builds the tree, then the ui gets the keys
+                // the way it does in a real run
+                frame.tick(backend)?;
+                TabIndex::new(&mut frame.tabindex, frame.tree.view()).next();
+                let mut ui = Ui {
+                    backend,
+                    server: &mut server,
+                    frame: &mut frame,
+                    farewell: farewell.clone(),
+                    stopped: false,
+                };
+                ui.tick();
+                (test.take().unwrap())(&mut ui);
+                Ok(())
+            })
+        })
+        .unwrap();
+}
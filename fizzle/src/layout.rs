@@ -0,0 +1,404 @@
+use crate::loom::PaneId;
+use serde::{Deserialize, Serialize};
+
+/// Smallest share of a split either side can be resized down to.
+const MIN_RATIO: f32 = 0.1;
+
+/// Which way a split divides its area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    /// Side by side, the divider runs top to bottom.
+    Horizontal,
+    /// One above the other, the divider runs left to right.
+    Vertical,
+}
+
+/// A cell aligned area of the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the cell at `x`, `y` is inside.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Cut in two along `axis`, `ratio` of it going to the first half and
+    /// one cell in between left free as the divider.
+    fn split(self, axis: Axis, ratio: f32) -> (Rect, Rect) {
+        let total = match axis {
+            Axis::Horizontal => self.width,
+            Axis::Vertical => self.height,
+        };
+        let usable = total.saturating_sub(1);
+        let first = ((usable as f32 * ratio).round() as u16).clamp(1.min(usable), usable);
+        let second = usable - first;
+        match axis {
+            Axis::Horizontal => (
+                Rect::new(self.x, self.y, first, self.height),
+                Rect::new(self.x + first + 1, self.y, second, self.height),
+            ),
+            Axis::Vertical => (
+                Rect::new(self.x, self.y, self.width, first),
+                Rect::new(self.x, self.y + first + 1, self.width, second),
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf(PaneId),
+    Split {
+        axis: Axis,
+        // share of the area going to `first`
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, id: PaneId) -> bool {
+        match self {
+            Node::Leaf(leaf) => *leaf == id,
+            Node::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    fn leaves(&self, out: &mut Vec<PaneId>) {
+        match self {
+            Node::Leaf(leaf) => out.push(*leaf),
+            Node::Split { first, second, .. } => {
+                first.leaves(out);
+                second.leaves(out);
+            }
+        }
+    }
+
+    fn split(&mut self, target: PaneId, new: PaneId, axis: Axis) -> bool {
+        match self {
+            Node::Leaf(leaf) if *leaf == target => {
+                *self = Node::Split {
+                    axis,
+                    ratio: 0.5,
+                    first: Box::new(Node::Leaf(target)),
+                    second: Box::new(Node::Leaf(new)),
+                };
+                true
+            }
+            Node::Leaf(_) => false,
+            Node::Split { first, second, .. } => {
+                first.split(target, new, axis) || second.split(target, new, axis)
+            }
+        }
+    }
+
+    /// This node without `id`. A split that loses one side is replaced by
+    /// the other, so the sibling grows into the freed space.
+    fn without(self, id: PaneId) -> Option<Node> {
+        match self {
+            Node::Leaf(leaf) if leaf == id => None,
+            Node::Leaf(_) => Some(self),
+            Node::Split {
+                axis,
+                ratio,
+                first,
+                second,
+            } => match (first.without(id), second.without(id)) {
+                (Some(first), Some(second)) => Some(Node::Split {
+                    axis,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(only), None) | (None, Some(only)) => Some(only),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// Move the closest divider along `axis` next to `target` so that
+    /// `target` grows by `delta` (or shrinks, if negative).
+    fn resize(&mut self, target: PaneId, axis: Axis, delta: f32) -> bool {
+        let Node::Split {
+            axis: own_axis,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let in_first = first.contains(target);
+        if !in_first && !second.contains(target) {
+            return false;
+        }
+        // a split further down takes precedence, it's closer to the pane
+        let child = if in_first { first } else { second };
+        if child.resize(target, axis, delta) {
+            return true;
+        }
+        if *own_axis != axis {
+            return false;
+        }
+        let delta = if in_first { delta } else { -delta };
+        *ratio = (*ratio + delta).clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        true
+    }
+
+    /// Put the divider after `target` along `axis`, the one right of it
+    /// or below it, at column or row `to` of the window, with `area`
+    /// being what this node covers.
+    fn move_divider(&mut self, target: PaneId, axis: Axis, to: u16, area: Rect) -> bool {
+        let Node::Split {
+            axis: own_axis,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let (a, b) = area.split(*own_axis, *ratio);
+        if !first.contains(target) {
+            return second.move_divider(target, axis, to, b);
+        }
+        // the divider after a split further down is closer to the pane
+        if first.move_divider(target, axis, to, a) {
+            return true;
+        }
+        if *own_axis != axis {
+            return false;
+        }
+        let (start, total) = match axis {
+            Axis::Horizontal => (area.x, area.width),
+            Axis::Vertical => (area.y, area.height),
+        };
+        let usable = total.saturating_sub(1);
+        if usable == 0 {
+            return false;
+        }
+        let wanted = to.saturating_sub(start) as f32 / usable as f32;
+        *ratio = wanted.clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        true
+    }
+
+    fn arrange(&self, area: Rect, out: &mut Vec<(PaneId, Rect)>) {
+        match self {
+            Node::Leaf(leaf) => out.push((*leaf, area)),
+            Node::Split {
+                axis,
+                ratio,
+                first,
+                second,
+            } => {
+                let (a, b) = area.split(*axis, *ratio);
+                first.arrange(a, out);
+                second.arrange(b, out);
+            }
+        }
+    }
+}
+
+/// How the panes share the screen: a tree of splits with a pane in every
+/// leaf.
+#[derive(Debug, Default)]
+pub struct Layout {
+    root: Option<Node>,
+}
+
+impl Layout {
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// All panes, left to right and top to bottom.
+    pub fn panes(&self) -> Vec<PaneId> {
+        let mut out = vec![];
+        if let Some(root) = &self.root {
+            root.leaves(&mut out);
+        }
+        out
+    }
+
+    /// Put `new` next to `target`, giving it half of `target`'s area.
+    /// The first pane goes in on its own.
+    pub fn split(&mut self, target: Option<PaneId>, new: PaneId, axis: Axis) -> bool {
+        match (&mut self.root, target) {
+            (None, _) => {
+                self.root = Some(Node::Leaf(new));
+                true
+            }
+            (Some(root), Some(target)) => root.split(target, new, axis),
+            (Some(_), None) => false,
+        }
+    }
+
+    pub fn remove(&mut self, id: PaneId) {
+        self.root = self.root.take().and_then(|root| root.without(id));
+    }
+
+    /// Grow `target` by `delta` of its split along `axis`.
+    pub fn resize(&mut self, target: PaneId, axis: Axis, delta: f32) -> bool {
+        self.root
+            .as_mut()
+            .is_some_and(|root| root.resize(target, axis, delta))
+    }
+
+    /// Drag the divider right of `target`, or below it for
+    /// [`Axis::Vertical`], to column or row `to` of `area`.
+    pub fn move_divider(&mut self, target: PaneId, axis: Axis, to: u16, area: Rect) -> bool {
+        self.root
+            .as_mut()
+            .is_some_and(|root| root.move_divider(target, axis, to, area))
+    }
+
+    /// Where every pane goes when the whole layout covers `area`.
+    pub fn arrange(&self, area: Rect) -> Vec<(PaneId, Rect)> {
+        let mut out = vec![];
+        if let Some(root) = &self.root {
+            root.arrange(area, &mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(n: u32) -> PaneId {
+        PaneId::from_raw(n)
+    }
+
+    // a | b, both as tall as the area
+    fn side_by_side() -> Layout {
+        let mut layout = Layout::default();
+        layout.split(None, pane(0), Axis::Horizontal);
+        layout.split(Some(pane(0)), pane(1), Axis::Horizontal);
+        layout
+    }
+
+    #[test]
+    fn a_split_leaves_a_cell_for_the_divider() {
+        let split = |width, ratio| Rect::new(0, 0, width, 5).split(Axis::Horizontal, ratio);
+        assert_eq!(
+            split(0, 0.5),
+            (Rect::new(0, 0, 0, 5), Rect::new(1, 0, 0, 5))
+        );
+        assert_eq!(
+            split(1, 0.5),
+            (Rect::new(0, 0, 0, 5), Rect::new(1, 0, 0, 5))
+        );
+        assert_eq!(
+            split(2, 0.5),
+            (Rect::new(0, 0, 1, 5), Rect::new(2, 0, 0, 5))
+        );
+        assert_eq!(
+            split(11, 0.5),
+            (Rect::new(0, 0, 5, 5), Rect::new(6, 0, 5, 5))
+        );
+        // the first side keeps a cell however small its share
+        assert_eq!(split(11, 0.0).0, Rect::new(0, 0, 1, 5));
+
+        let (top, bottom) = Rect::new(2, 3, 4, 9).split(Axis::Vertical, 0.25);
+        assert_eq!(
+            (top, bottom),
+            (Rect::new(2, 3, 4, 2), Rect::new(2, 6, 4, 6))
+        );
+    }
+
+    #[test]
+    fn resizing_stops_short_of_squashing_a_side() {
+        let area = Rect::new(0, 0, 61, 5);
+        let mut layout = side_by_side();
+        assert!(layout.resize(pane(0), Axis::Horizontal, 1.0));
+        assert_eq!(
+            layout.arrange(area),
+            [
+                (pane(0), Rect::new(0, 0, 54, 5)),
+                (pane(1), Rect::new(55, 0, 6, 5)),
+            ]
+        );
+        // growing the second pane moves the divider the other way
+        assert!(layout.resize(pane(1), Axis::Horizontal, 1.0));
+        assert_eq!(layout.arrange(area)[0].1.width, 6);
+        // there's no split that way, or no such pane
+        assert!(!layout.resize(pane(0), Axis::Vertical, 0.1));
+        assert!(!layout.resize(pane(7), Axis::Horizontal, 0.1));
+    }
+
+    #[test]
+    fn a_divider_is_dragged_within_the_same_bounds() {
+        let area = Rect::new(0, 0, 61, 5);
+        let mut layout = side_by_side();
+        assert!(layout.move_divider(pane(0), Axis::Horizontal, 20, area));
+        assert_eq!(layout.arrange(area)[0].1.width, 20);
+        assert!(layout.move_divider(pane(0), Axis::Horizontal, 0, area));
+        assert_eq!(layout.arrange(area)[0].1.width, 6);
+        assert!(layout.move_divider(pane(0), Axis::Horizontal, 200, area));
+        assert_eq!(layout.arrange(area)[0].1.width, 54);
+        // the last pane has no divider after it
+        assert!(!layout.move_divider(pane(1), Axis::Horizontal, 20, area));
+        assert!(!layout.move_divider(pane(0), Axis::Vertical, 2, area));
+    }
+
+    #[test]
+    fn closing_a_pane_gives_its_room_to_its_sibling() {
+        let area = Rect::new(0, 0, 61, 21);
+        let mut layout = side_by_side();
+        layout.split(Some(pane(1)), pane(2), Axis::Vertical);
+        assert_eq!(layout.panes(), [pane(0), pane(1), pane(2)]);
+
+        layout.remove(pane(1));
+        assert_eq!(
+            layout.arrange(area),
+            [
+                (pane(0), Rect::new(0, 0, 30, 21)),
+                (pane(2), Rect::new(31, 0, 30, 21)),
+            ]
+        );
+        layout.remove(pane(0));
+        assert_eq!(layout.arrange(area), [(pane(2), area)]);
+        layout.remove(pane(2));
+        assert!(layout.is_empty());
+    }
+
+    #[test]
+    fn splits_inside_splits_keep_their_own_ratios() {
+        let area = Rect::new(0, 0, 61, 21);
+        let mut layout = side_by_side();
+        layout.move_divider(pane(0), Axis::Horizontal, 20, area);
+        layout.split(Some(pane(1)), pane(2), Axis::Vertical);
+        layout.resize(pane(1), Axis::Vertical, -0.25);
+        assert_eq!(
+            layout.arrange(area),
+            [
+                (pane(0), Rect::new(0, 0, 20, 21)),
+                (pane(1), Rect::new(21, 0, 40, 5)),
+                (pane(2), Rect::new(21, 6, 40, 15)),
+            ]
+        );
+        // the outer split is the one across, so only it moves
+        layout.resize(pane(2), Axis::Horizontal, 0.1);
+        let arranged = layout.arrange(area);
+        assert_eq!(arranged[0].1.width, 14);
+        assert_eq!(arranged[1].1.height, 5);
+        assert_eq!(arranged[2].1, Rect::new(15, 6, 46, 15));
+    }
+}
@@ -0,0 +1,618 @@
+use crate::copy::{CopyAction, CopyMode, CopyOutcome};
+use crate::search::SearchStatus;
+use alacrema_term::{Alerts, DEFAULT_HISTORY_LIMIT, Terminal};
+use anyhow::anyhow;
+use futures::prelude::*;
+use portable_pty::{
+    Child, CommandBuilder, ExitStatus, MasterPty, PtySize, PtySystem, native_pty_system,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What's running in a pane and where, for the statusline.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaneInfo {
+    /// The name of the process in the foreground.
+    pub command: String,
+    pub cwd: String,
+    /// How the last command exited, for shells that say so.
+    pub status: Option<i32>,
+    /// How the pane's own process ended, once it has.
+    pub exit: Option<String>,
+}
+
+/// What happens to a pane once its process exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitPolicy {
+    /// Stay on screen saying how it went, until closed or respawned.
+    Keep,
+    #[default]
+    Close,
+    /// Start the command again.
+    Respawn,
+}
+
+impl ExitPolicy {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "close" => Ok(Self::Close),
+            "respawn" => Ok(Self::Respawn),
+            _ => Err(anyhow!("expected keep, close or respawn, not {s:?}")),
+        }
+    }
+}
+
+/// How a pane's process ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaneExit {
+    pub command: String,
+    pub code: u32,
+    /// The signal that ended it, if it didn't exit by itself.
+    pub signal: Option<String>,
+    pub runtime: Duration,
+}
+
+impl PaneExit {
+    fn new(command: String, status: &ExitStatus, runtime: Duration) -> Self {
+        Self {
+            command,
+            code: status.exit_code(),
+            signal: status.signal().map(str::to_string),
+            runtime,
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.signal.is_some() || self.code != 0
+    }
+
+    /// How it ended, without the command or how long it ran.
+    pub fn summary(&self) -> String {
+        match (&self.signal, self.code) {
+            (Some(signal), _) => format!("killed by {signal}"),
+            (None, 0) => "exited".to_string(),
+            (None, code) => format!("exited with code {code}"),
+        }
+    }
+}
+
+impl fmt::Display for PaneExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.runtime.as_secs();
+        write!(f, "{} {} after ", self.command, self.summary())?;
+        match secs {
+            0..60 => write!(f, "{secs}s"),
+            60..3600 => write!(f, "{}m{:02}s", secs / 60, secs % 60),
+            _ => write!(f, "{}h{:02}m", secs / 3600, secs / 60 % 60),
+        }
+    }
+}
+
+/// Stable handle for a pane, handed out by the loom and never reused
+/// for the lifetime of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PaneId(u32);
+
+impl PaneId {
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+
+    pub fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl fmt::Display for PaneId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+/// Every pane's emulated screen by id. Cloneable, so components that only
+/// draw panes don't need the loom itself.
+#[derive(Clone, Default)]
+pub struct ScreenRegistry(Arc<Mutex<HashMap<PaneId, Arc<Mutex<Terminal>>>>>);
+
+impl ScreenRegistry {
+    pub fn get(&self, id: PaneId) -> Option<Arc<Mutex<Terminal>>> {
+        self.0.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn insert(&self, id: PaneId, terminal: Arc<Mutex<Terminal>>) {
+        self.0.lock().unwrap().insert(id, terminal);
+    }
+
+    fn remove(&self, id: PaneId) {
+        self.0.lock().unwrap().remove(&id);
+    }
+
+    /// Forget every screen but the ones for `panes`.
+    pub fn retain(&self, panes: &[PaneId]) {
+        self.0.lock().unwrap().retain(|id, _| panes.contains(id));
+    }
+}
+
+/// One shell (or other command) running on its own pty.
+pub struct Pane {
+    id: PaneId,
+    title: String,
+    master: Box<dyn MasterPty + Send>,
+    // rows, cols as last told to the pty
+    size: (u16, u16),
+    child: Box<dyn Child + Send + Sync>,
+    started: Instant,
+    exit: Option<PaneExit>,
+    exit_policy: ExitPolicy,
+    // looking through the scrollback instead of following output, and
+    // how often that changed the view, on top of the terminal's generation
+    copy: Option<CopyMode>,
+    copy_changes: u64,
+    // shared with the reader, which has to answer terminal queries
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    terminal: Arc<Mutex<Terminal>>,
+    _reader: smol::Task<()>,
+}
+
+impl Pane {
+    pub fn id(&self) -> PaneId {
+        self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// How the process ended, if it has.
+    pub fn exit(&self) -> Option<&PaneExit> {
+        self.exit.as_ref()
+    }
+
+    pub fn exit_policy(&self) -> ExitPolicy {
+        self.exit_policy
+    }
+
+    pub fn in_copy_mode(&self) -> bool {
+        self.copy.is_some()
+    }
+
+    /// How the search in copy mode went, if there is one.
+    pub fn search_status(&self) -> Option<SearchStatus> {
+        self.copy.as_ref()?.search_status().cloned()
+    }
+
+    /// `(offset, lines)`: how far back the view is and how much
+    /// scrollback there is.
+    pub fn scroll_position(&self) -> (usize, usize) {
+        let terminal = self.terminal.lock().unwrap();
+        let screen = terminal.screen();
+        (screen.display_offset(), screen.history_len())
+    }
+
+    /// Goes up whenever what the pane shows changes.
+    pub fn generation(&self) -> u64 {
+        let terminal = self.terminal.lock().unwrap();
+        terminal.generation().wrapping_add(self.copy_changes)
+    }
+
+    /// `(rows, cols, dump)`: escape sequences drawing what the pane shows
+    /// on a fresh `rows` x `cols` terminal, copy mode's cursor and
+    /// selection included.
+    pub fn snapshot(&self) -> (u16, u16, Vec<u8>) {
+        let terminal = self.terminal.lock().unwrap();
+        let screen = terminal.screen();
+        let dump = match &self.copy {
+            Some(copy) => copy.dump(screen),
+            None => screen.dump(),
+        };
+        (screen.lines() as u16, screen.cols() as u16, dump)
+    }
+
+    /// Whether the process asked for pastes to be bracketed.
+    pub fn bracketed_paste(&self) -> bool {
+        let terminal = self.terminal.lock().unwrap();
+        terminal.screen().modes().bracketed_paste
+    }
+
+    /// Look up what's in the foreground. Linux has it in `/proc`,
+    /// elsewhere it's the title and whatever the shell reports itself.
+    pub fn info(&self) -> PaneInfo {
+        let (title, cwd, status) = {
+            let terminal = self.terminal.lock().unwrap();
+            let screen = terminal.screen();
+            let cwd = screen.cwd().map(str::to_string);
+            (screen.title().to_string(), cwd, screen.last_status())
+        };
+        let proc = self
+            .master
+            .process_group_leader()
+            .map(|pid| std::path::PathBuf::from(format!("/proc/{pid}")));
+        let command = proc
+            .as_ref()
+            .and_then(|dir| std::fs::read_to_string(dir.join("comm")).ok())
+            .map(|comm| comm.trim_end().to_string())
+            .unwrap_or_else(|| self.title.clone());
+        // the shell's own word on its cwd goes before the foreground
+        // process's, which may be somewhere else entirely
+        let cwd = cwd.or_else(|| {
+            let link = std::fs::read_link(proc?.join("cwd")).ok()?;
+            Some(link.to_string_lossy().into_owned())
+        });
+        PaneInfo {
+            command: if command.is_empty() { title } else { command },
+            cwd: cwd.unwrap_or_default(),
+            status,
+            exit: self.exit.as_ref().map(PaneExit::summary),
+        }
+    }
+
+    /// How the pane's process exited, the first time this is asked after
+    /// it did.
+    pub fn check_exit(&mut self) -> Option<PaneExit> {
+        if self.exit.is_some() {
+            return None;
+        }
+        let status = self.child.try_wait().ok()??;
+        let exit = PaneExit::new(self.title.clone(), &status, self.started.elapsed());
+        // a full screen program that didn't get to clean up leaves its
+        // screen and modes behind otherwise
+        self.terminal.lock().unwrap().reset_modes();
+        self.exit = Some(exit.clone());
+        Some(exit)
+    }
+
+    /// Output and bells since the last call.
+    pub fn take_alerts(&self) -> Alerts {
+        self.terminal.lock().unwrap().take_alerts()
+    }
+
+    /// Send raw bytes to the process running in this pane.
+    pub fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(bytes)?;
+        writer.flush()
+    }
+}
+
+impl Drop for Pane {
+    fn drop(&mut self) {
+        // don't leave shells orphaned behind us
+        let _ = self.child.kill();
+    }
+}
+
+pub struct PseudoTerminalLoom {
+    pty_system: Option<Box<dyn PtySystem + Send>>,
+    panes: Vec<Pane>,
+    screens: ScreenRegistry,
+    next_pane_id: u32,
+    command: Vec<OsString>,
+    // set on top of what the loom's own process has
+    env: Vec<(String, String)>,
+    history_limit: usize,
+    exit_policy: ExitPolicy,
+}
+
+impl PseudoTerminalLoom {
+    pub fn new() -> Self {
+        let shell = std::env::var_os("SHELL").unwrap_or_else(|| "/bin/sh".into());
+        Self::with_command(vec![shell])
+    }
+
+    /// Use `argv` instead of `$SHELL` for every pane spawned from now on.
+    pub fn with_command(argv: Vec<OsString>) -> Self {
+        Self {
+            pty_system: None,
+            panes: vec![],
+            screens: ScreenRegistry::default(),
+            next_pane_id: 0,
+            command: argv,
+            env: vec![],
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            exit_policy: ExitPolicy::default(),
+        }
+    }
+
+    /// Run `argv` in every pane spawned from now on.
+    pub fn set_command(&mut self, argv: Vec<OsString>) {
+        self.command = argv;
+    }
+
+    /// Give every pane spawned from now on these environment variables.
+    pub fn set_env(&mut self, env: Vec<(String, String)>) {
+        self.env = env;
+    }
+
+    /// Keep up to `lines` of scrollback for every pane spawned from now on.
+    pub fn set_history_limit(&mut self, lines: usize) {
+        self.history_limit = lines;
+    }
+
+    /// What panes spawned from now on do when their process exits.
+    pub fn set_exit_policy(&mut self, policy: ExitPolicy) {
+        self.exit_policy = policy;
+    }
+
+    fn init_pty_system(&mut self) {
+        self.pty_system = Some(native_pty_system());
+    }
+
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    pub fn panes_mut(&mut self) -> &mut [Pane] {
+        &mut self.panes
+    }
+
+    pub fn pane(&self, id: PaneId) -> Option<&Pane> {
+        self.panes.iter().find(|p| p.id == id)
+    }
+
+    pub fn pane_mut(&mut self, id: PaneId) -> Option<&mut Pane> {
+        self.panes.iter_mut().find(|p| p.id == id)
+    }
+
+    fn expect_pane_mut(&mut self, id: PaneId) -> anyhow::Result<&mut Pane> {
+        self.pane_mut(id)
+            .ok_or_else(|| anyhow!("no such pane {id}"))
+    }
+
+    /// Kill the pane's process and forget about it.
+    pub fn close_pane(&mut self, id: PaneId) -> anyhow::Result<()> {
+        let index = self
+            .panes
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| anyhow!("no such pane {id}"))?;
+        // dropping the pane kills its child and stops the reader
+        self.panes.remove(index);
+        self.screens.remove(id);
+        Ok(())
+    }
+
+    /// Resize the pane's pty, which sends its process SIGWINCH, and reflow
+    /// the emulated screen to match.
+    pub fn resize(&mut self, id: PaneId, rows: u16, cols: u16) -> anyhow::Result<()> {
+        // a pty can't be 0 cells wide or tall, same as at launch
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let pane = self.expect_pane_mut(id)?;
+        if pane.size == (rows, cols) {
+            return Ok(());
+        }
+        pane.master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        pane.terminal
+            .lock()
+            .unwrap()
+            .resize(rows as usize, cols as usize);
+        pane.size = (rows, cols);
+        Ok(())
+    }
+
+    pub fn write(&mut self, id: PaneId, bytes: &[u8]) -> anyhow::Result<()> {
+        self.expect_pane_mut(id)?.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Enter or leave copy mode. Leaving goes back to the live screen.
+    pub fn set_copy_mode(&mut self, id: PaneId, on: bool) -> anyhow::Result<()> {
+        let pane = self.expect_pane_mut(id)?;
+        let mut terminal = pane.terminal.lock().unwrap();
+        match (on, pane.copy.is_some()) {
+            (true, false) => pane.copy = Some(CopyMode::new(terminal.screen())),
+            (false, true) => {
+                pane.copy = None;
+                terminal.reset_display();
+            }
+            _ => return Ok(()),
+        }
+        pane.copy_changes += 1;
+        Ok(())
+    }
+
+    /// Move the view of a pane in copy mode `lines` back into its
+    /// scrollback, or forward if negative.
+    pub fn scroll(&mut self, id: PaneId, lines: isize) -> anyhow::Result<()> {
+        let pane = self.expect_pane_mut(id)?;
+        let Some(copy) = pane.copy.as_mut() else {
+            return Err(anyhow!("pane {id} isn't in copy mode"));
+        };
+        let mut terminal = pane.terminal.lock().unwrap();
+        let before = terminal.screen().display_offset();
+        terminal.scroll_display(lines);
+        let screen = terminal.screen();
+        copy.scrolled(screen, screen.display_offset() as isize - before as isize);
+        Ok(())
+    }
+
+    /// Handle a key of copy mode, which ends it when leaving or yanking.
+    pub fn copy(&mut self, id: PaneId, action: CopyAction) -> anyhow::Result<CopyOutcome> {
+        let pane = self.expect_pane_mut(id)?;
+        let Some(copy) = pane.copy.as_mut() else {
+            return Err(anyhow!("pane {id} isn't in copy mode"));
+        };
+        let mut terminal = pane.terminal.lock().unwrap();
+        let outcome = copy.apply(action, &mut terminal);
+        if !matches!(outcome, CopyOutcome::Stay) {
+            pane.copy = None;
+            terminal.reset_display();
+        }
+        pane.copy_changes += 1;
+        Ok(outcome)
+    }
+
+    /// Title a pane, which its window's tab shows unless the window has
+    /// a name. Nothing goes back to the command's name.
+    pub fn rename(&mut self, id: PaneId, title: String) -> anyhow::Result<()> {
+        let title = if title.is_empty() {
+            cmd_title(&self.command)
+        } else {
+            title
+        };
+        self.expect_pane_mut(id)?.title = title;
+        Ok(())
+    }
+
+    /// What this pane does when its process exits, overriding the loom's.
+    pub fn set_pane_exit_policy(&mut self, id: PaneId, policy: ExitPolicy) -> anyhow::Result<()> {
+        self.expect_pane_mut(id)?.exit_policy = policy;
+        Ok(())
+    }
+
+    /// Open a new `rows` x `cols` pty, start the loom's command on it and
+    /// begin reading its output in the background.
+    pub fn spawn_pty(&mut self, rows: u16, cols: u16) -> anyhow::Result<PaneId> {
+        let id = PaneId(self.next_pane_id);
+        let pane = self.launch(id, rows, cols, self.exit_policy)?;
+        self.next_pane_id += 1;
+        self.panes.push(pane);
+        Ok(id)
+    }
+
+    /// Start the loom's command over in a pane, on a fresh screen of the
+    /// same size. One still running is only killed for it if `kill`.
+    pub fn respawn(&mut self, id: PaneId, kill: bool) -> anyhow::Result<()> {
+        let index = self
+            .panes
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| anyhow!("no such pane {id}"))?;
+        let old = &mut self.panes[index];
+        if old.exit.is_none() && old.child.try_wait()?.is_none() && !kill {
+            return Err(anyhow!("pane {id} is still running, use respawn-pane -k"));
+        }
+        let (rows, cols) = old.size;
+        let policy = old.exit_policy;
+        // clients go by the generation to know when to send the screen,
+        // it has to keep going up for the new one
+        let generation = old.generation();
+        let mut pane = self.launch(id, rows, cols, policy)?;
+        pane.copy_changes = generation + 1;
+        // the old pane is dropped in its place, which kills what's left of it
+        self.panes[index] = pane;
+        Ok(())
+    }
+
+    fn launch(
+        &mut self,
+        id: PaneId,
+        rows: u16,
+        cols: u16,
+        exit_policy: ExitPolicy,
+    ) -> anyhow::Result<Pane> {
+        if self.pty_system.is_none() {
+            self.init_pty_system();
+        }
+
+        let pty_system = self.pty_system.as_ref().unwrap();
+        let size = PtySize {
+            rows: rows.max(1),
+            cols: cols.max(1),
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = pty_system.openpty(size)?;
+
+        let mut cmd = if self.command.is_empty() {
+            CommandBuilder::new_default_prog()
+        } else {
+            CommandBuilder::from_argv(self.command.clone())
+        };
+        if let Ok(cwd) = std::env::current_dir() {
+            cmd.cwd(cwd);
+        }
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+
+        let title = cmd_title(&self.command);
+        let child = pair.slave.spawn_command(cmd)?;
+        // NOTE: the child holds its own copy of the slave, keeping ours
+        // would stop the reader from ever seeing EOF
+        drop(pair.slave);
+
+        let writer = Arc::new(Mutex::new(pair.master.take_writer()?));
+        let reader = pair.master.try_clone_reader()?;
+
+        let mut terminal = Terminal::new(size.rows as usize, size.cols as usize);
+        terminal.set_history_limit(self.history_limit);
+        let terminal = Arc::new(Mutex::new(terminal));
+        let reader_task = {
+            let terminal = terminal.clone();
+            let writer = writer.clone();
+            smol::spawn(async move {
+                let mut reader = smol::Unblock::new(reader);
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = match reader.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    let replies = {
+                        let mut terminal = terminal.lock().unwrap();
+                        terminal.advance(&buf[..n]);
+                        terminal.take_replies()
+                    };
+                    if !replies.is_empty() {
+                        let mut writer = writer.lock().unwrap();
+                        _ = writer.write_all(&replies).and_then(|_| writer.flush());
+                    }
+                }
+            })
+        };
+
+        self.screens.insert(id, terminal.clone());
+        Ok(Pane {
+            id,
+            title,
+            master: pair.master,
+            size: (size.rows, size.cols),
+            child,
+            started: Instant::now(),
+            exit: None,
+            exit_policy,
+            copy: None,
+            copy_changes: 0,
+            writer,
+            terminal,
+            _reader: reader_task,
+        })
+    }
+}
+
+fn cmd_title(argv: &[OsString]) -> String {
+    match argv.first() {
+        Some(program) => program.to_string_lossy().into_owned(),
+        None => "shell".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_collapsed_pane_keeps_a_cell() {
+        let mut loom = PseudoTerminalLoom::with_command(vec!["cat".into()]);
+        let id = loom.spawn_pty(0, 0).unwrap();
+        assert_eq!(loom.pane(id).unwrap().size, (1, 1));
+        loom.resize(id, 5, 20).unwrap();
+        loom.resize(id, 0, 20).unwrap();
+        let pane = loom.pane(id).unwrap();
+        assert_eq!(pane.size, (1, 20));
+        assert_eq!(pane.master.get_size().unwrap().rows, 1);
+        assert_eq!(pane.snapshot().0, 1);
+    }
+}
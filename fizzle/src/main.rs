@@ -1,97 +1,660 @@
-use anathema::backend::tui::Style;
+use alacrema_templates::Templates;
+use alacrema_term::render::{self, Look};
+use alacrema_term::{Modes, MouseTracking, TermCanvas, Terminal, canvas, input};
+use anathema::backend::tui::{Attributes, Style};
 use anathema::component::*;
-use anathema::default_widgets::Canvas;
 use anathema::prelude::*;
-use portable_pty::PtyPair;
-use portable_pty::PtySystem;
 use std::cmp::max;
-use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 extern crate rand;
 extern crate rand_chacha;
 use rand::{Rng, SeedableRng};
 
-// use anyhow::anyhow;
-// use futures::prelude::*;
-// use std::ffi::OsString;
-
-use portable_pty::PtySize;
-use portable_pty::native_pty_system;
-// use portable_pty::{CommandBuilder, PtySize};
+mod binding;
+mod border;
+mod cli;
+mod client;
+mod clock;
+mod command;
+mod config;
+mod copy;
+#[cfg(test)]
+mod headless;
+mod layout;
+mod loom;
+mod notice;
+mod prefix;
+mod prompt;
+mod protocol;
+mod search;
+mod server;
+mod session;
+mod status;
+mod window;
+use binding::{Bindings, Command, Direction};
+use cli::{Action, USAGE};
+use client::Connection;
+use command::{COMMANDS_PER_TICK, CommandQueue, UserRequestType};
+use config::{ColorPair, Colors, Settings};
+use copy::{CopyAction, ModeKeys, Motion};
+use layout::{Axis, Rect};
+use loom::{PaneId, ScreenRegistry};
+use notice::{Level, Log, Notice};
+use prefix::{KeyRoute, PrefixKey, PrefixState};
+use prompt::{Prompt, PromptEvent, PromptKind};
+use protocol::{ClientMessage, ServerMessage};
+use search::Query;
+use session::{Tab, View};
+use status::{Clock, Segment, Vars};
+
+/// The templates built into the binary, by file name.
+const TEMPLATES: &[(&str, &str)] = &[
+    ("ui.aml", include_str!("ui.aml")),
+    ("statusline.aml", include_str!("statusline.aml")),
+    ("statusfeed.aml", include_str!("statusfeed.aml")),
+    ("canvasfx.aml", include_str!("canvasfx.aml")),
+];
 
 const BUBBLE: &str = "·⋅◌⊙⊚⦾⁜";
 
-enum UserRequestType {
-    NewPTY,
+/// How many lines a turn of the mouse wheel scrolls.
+const WHEEL_LINES: isize = 3;
+
+/// How long `display-panes` leaves the numbers up.
+const NUMBERS_SHOWN: Duration = Duration::from_secs(1);
+
+/// Where one pane's canvas goes, for ui.aml to lay out.
+#[derive(State)]
+struct PaneView {
+    id: Value<i64>,
+    x: Value<u16>,
+    y: Value<u16>,
+    width: Value<u16>,
+    height: Value<u16>,
+    // how its process ended, drawn over the screen, or empty while it runs
+    exit: Value<String>,
+    focused: Value<bool>,
+    dim: Value<bool>,
+    // what display-panes shows on it, and in which colour
+    number: Value<String>,
+    accent: Value<String>,
 }
 
-#[derive(Default)]
-struct CommandQueue {
-    vd: VecDeque<UserRequestType>,
+/// How a pane is drawn, besides what's on its screen.
+#[derive(Debug, Default)]
+struct PaneLook {
+    exit: String,
+    focused: bool,
+    dim: bool,
+    number: String,
+    accent: String,
+}
+
+impl PaneView {
+    fn new(id: PaneId, rect: Rect, look: PaneLook) -> Self {
+        Self {
+            id: (id.raw() as i64).into(),
+            x: rect.x.into(),
+            y: rect.y.into(),
+            width: rect.width.into(),
+            height: rect.height.into(),
+            exit: look.exit.into(),
+            focused: look.focused.into(),
+            dim: look.dim.into(),
+            number: look.number.into(),
+            accent: look.accent.into(),
+        }
+    }
+
+    fn update(&mut self, id: PaneId, rect: Rect, look: PaneLook) {
+        // only touch what moved, every set means another layout pass
+        fn set<T: State + PartialEq + Copy>(value: &mut Value<T>, new: T) {
+            if value.copy_value() != new {
+                value.set(new);
+            }
+        }
+        fn set_text(value: &mut Value<String>, new: String) {
+            if *value.to_ref() != new {
+                value.set(new);
+            }
+        }
+        set(&mut self.id, id.raw() as i64);
+        set(&mut self.x, rect.x);
+        set(&mut self.y, rect.y);
+        set(&mut self.width, rect.width);
+        set(&mut self.height, rect.height);
+        set_text(&mut self.exit, look.exit);
+        set(&mut self.focused, look.focused);
+        set(&mut self.dim, look.dim);
+        set_text(&mut self.number, look.number);
+        set_text(&mut self.accent, look.accent);
+    }
 }
 
 #[derive(State)]
 struct UIMainState {
     #[anathema(ignore)]
     command_queue: CommandQueue,
+    #[anathema(ignore)]
+    connection: Connection,
+    #[anathema(ignore)]
+    prefix: PrefixState,
+    #[anathema(ignore)]
+    prompt: Option<Prompt>,
+    #[anathema(ignore)]
+    mode_keys: ModeKeys,
+    #[anathema(ignore)]
+    bindings: Bindings,
+    // what was taken from the config for the statusline
+    #[anathema(ignore)]
+    colors: Colors,
+    #[anathema(ignore)]
+    segments: Vec<Segment>,
+    #[anathema(ignore)]
+    max_fps: i32,
+    // the session's name, for the statusline
+    #[anathema(ignore)]
+    session: String,
+    #[anathema(ignore)]
+    notice_seconds: u64,
+    // every notice heard of, from the server or the client itself
+    #[anathema(ignore)]
+    log: Log,
+    // the notices since the last tick, for the statusfeed
+    #[anathema(ignore)]
+    fresh: Vec<Notice>,
+    // the session as the server last described it
+    #[anathema(ignore)]
+    view: View,
+    // copies of the screens in `view`, kept up to date by the server
+    #[anathema(ignore)]
+    screens: ScreenRegistry,
+    // the room for panes the server was last told about
+    #[anathema(ignore)]
+    area: (u16, u16),
+    // where that room starts on screen, mouse events come in screen
+    // coordinates
+    #[anathema(ignore)]
+    area_at: (u16, u16),
+    // the divider being dragged, as the pane before it, which way it
+    // runs and the column or row it was last sent to
+    #[anathema(ignore)]
+    drag: Option<(PaneId, Axis, u16)>,
+    // when display-panes put the numbers up, while they're up
+    #[anathema(ignore)]
+    numbers_at: Option<Instant>,
+    #[anathema(ignore)]
+    dim_inactive: bool,
+    #[anathema(ignore)]
+    detach: bool,
+    panes: Value<List<PaneView>>,
+    // anathema can't branch on a list, so this says if `panes` has any
+    has_panes: Value<bool>,
+    // the message log, shown in place of the panes while it's open
+    show_messages: Value<bool>,
+    messages: Value<List<String>>,
     fps: Value<i32>,
+    statusline_fg: Value<String>,
+    statusline_bg: Value<String>,
+    statusfeed_fg: Value<String>,
+    statusfeed_bg: Value<String>,
 }
 
-struct PseudoTerminalLoom {
-    pty_system: Option<Box<dyn PtySystem + Send>>,
-    ptys: Vec<PtyPair>,
-}
-
-impl PseudoTerminalLoom {
-    fn _new() -> Self {
+impl UIMainState {
+    fn new(
+        connection: Connection,
+        screens: ScreenRegistry,
+        settings: Settings,
+        session: String,
+    ) -> Self {
+        let config = settings.config;
         Self {
-            pty_system: None,
-            ptys: vec![],
+            command_queue: CommandQueue::default(),
+            connection,
+            prefix: PrefixState::new(settings.prefix),
+            prompt: None,
+            mode_keys: settings.mode_keys,
+            bindings: settings.bindings,
+            segments: settings.segments,
+            max_fps: config.max_fps,
+            session,
+            notice_seconds: config.notice_seconds,
+            log: Log::default(),
+            fresh: vec![],
+            statusline_fg: config.colors.statusline.fg.clone().into(),
+            statusline_bg: config.colors.statusline.bg.clone().into(),
+            statusfeed_fg: config.colors.statusfeed.fg.clone().into(),
+            statusfeed_bg: config.colors.statusfeed.bg.clone().into(),
+            colors: config.colors,
+            view: View::default(),
+            screens,
+            area: (0, 0),
+            area_at: (0, 0),
+            drag: None,
+            numbers_at: None,
+            dim_inactive: config.dim_inactive,
+            detach: false,
+            panes: List::empty().into(),
+            has_panes: false.into(),
+            show_messages: false.into(),
+            messages: List::empty().into(),
+            fps: config.fps.into(),
         }
     }
 
-    #[allow(dead_code)]
-    fn init_pty_system(&mut self) {
-        self.pty_system = Some(native_pty_system());
+    /// Take up a config that was read again. What the server does with it
+    /// is up to the server.
+    fn reconfigure(&mut self, settings: Settings) {
+        let config = settings.config;
+        self.prefix = PrefixState::new(settings.prefix);
+        self.mode_keys = settings.mode_keys;
+        self.bindings = settings.bindings;
+        self.segments = settings.segments;
+        self.max_fps = config.max_fps;
+        self.notice_seconds = config.notice_seconds;
+        self.dim_inactive = config.dim_inactive;
+        let fps = self.fps.copy_value().min(self.max_fps);
+        if fps != self.fps.copy_value() {
+            self.fps.set(fps);
+        }
+        self.statusline_fg.set(config.colors.statusline.fg.clone());
+        self.statusline_bg.set(config.colors.statusline.bg.clone());
+        self.statusfeed_fg.set(config.colors.statusfeed.fg.clone());
+        self.statusfeed_bg.set(config.colors.statusfeed.bg.clone());
+        self.colors = config.colors;
+    }
+
+    fn focused(&self) -> Option<PaneId> {
+        self.view.focused
     }
 
-    #[allow(dead_code)]
-    async fn spawn_pty(&mut self) -> anyhow::Result<()> {
-        if self.pty_system.is_none() {
-            self.init_pty_system();
+    /// The pane after the focused one, wrapping around.
+    fn next_pane(&self) -> Option<PaneId> {
+        let panes = &self.view.panes;
+        let current = panes.iter().position(|(id, _)| Some(*id) == self.focused());
+        let next = current.map_or(0, |i| (i + 1) % panes.len().max(1));
+        panes.get(next).map(|(id, _)| *id)
+    }
+
+    /// The pane across the divider from the focused one towards
+    /// `direction`. Of several, the one alongside it the most.
+    fn pane_towards(&self, direction: Direction) -> Option<PaneId> {
+        let focused = self.focused()?;
+        let (_, from) = self.view.panes.iter().find(|(id, _)| *id == focused)?;
+        let overlap = |a: u16, a_len: u16, b: u16, b_len: u16| {
+            (a + a_len).min(b + b_len).saturating_sub(a.max(b))
+        };
+        let mut best = None;
+        for (id, rect) in &self.view.panes {
+            let (touches, alongside) = match direction {
+                Direction::Left => (
+                    rect.x + rect.width + 1 == from.x,
+                    overlap(rect.y, rect.height, from.y, from.height),
+                ),
+                Direction::Right => (
+                    from.x + from.width + 1 == rect.x,
+                    overlap(rect.y, rect.height, from.y, from.height),
+                ),
+                Direction::Up => (
+                    rect.y + rect.height + 1 == from.y,
+                    overlap(rect.x, rect.width, from.x, from.width),
+                ),
+                Direction::Down => (
+                    from.y + from.height + 1 == rect.y,
+                    overlap(rect.x, rect.width, from.x, from.width),
+                ),
+            };
+            if touches && alongside > best.map_or(0, |(_, most)| most) {
+                best = Some((*id, alongside));
+            }
         }
+        best.map(|(id, _)| id)
+    }
 
-        let pty_system = self.pty_system.as_ref().unwrap();
-        self.ptys.push(pty_system.openpty(PtySize {
-            rows: 8,
-            cols: 16,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?);
-        Ok(())
+    /// The pane at screen column `x`, row `y`, and where that is in it.
+    fn pane_at(&self, x: u16, y: u16) -> Option<(PaneId, (u16, u16))> {
+        let x = x.checked_sub(self.area_at.0)?;
+        let y = y.checked_sub(self.area_at.1)?;
+        self.view
+            .panes
+            .iter()
+            .find(|(_, rect)| rect.contains(x, y))
+            .map(|(id, rect)| (*id, (x - rect.x, y - rect.y)))
     }
-}
 
-impl UIMainState {
-    fn new() -> Self {
-        Self {
-            command_queue: CommandQueue::default(),
-            fps: 24.into(),
+    /// The divider at screen column `x`, row `y`, as the pane left of it
+    /// or above it and which way it runs.
+    fn divider_at(&self, x: u16, y: u16) -> Option<(PaneId, Axis)> {
+        let x = x.checked_sub(self.area_at.0)?;
+        let y = y.checked_sub(self.area_at.1)?;
+        self.view.panes.iter().find_map(|(id, rect)| {
+            let rows = rect.y..rect.y + rect.height;
+            let cols = rect.x..rect.x + rect.width;
+            if rect.x + rect.width == x && rows.contains(&y) {
+                Some((*id, Axis::Horizontal))
+            } else if rect.y + rect.height == y && cols.contains(&x) {
+                Some((*id, Axis::Vertical))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The window whose tab is at column `x` of the statusline, which
+    /// starts with the prefix key.
+    fn tab_at(&self, x: u16) -> Option<usize> {
+        let mut left = format!(" {} ", self.prefix.key()).width();
+        for (i, tab) in self.view.tabs.iter().enumerate() {
+            let right = left + tab.label.width();
+            if (left..right).contains(&(x as usize)) {
+                return Some(i);
+            }
+            left = right;
+        }
+        None
+    }
+
+    /// Bring `panes` in line with the view, for the template to follow.
+    fn sync_views(&mut self) {
+        let arranged = &self.view.panes;
+        while self.panes.len() > arranged.len() {
+            self.panes.pop_back();
+        }
+        let numbered = self.numbers_at.is_some();
+        for (i, (id, rect)) in arranged.iter().enumerate() {
+            let exit = self
+                .view
+                .exits
+                .iter()
+                .find(|(pane, _)| pane == id)
+                .map_or(String::new(), |(_, exit)| exit.to_string());
+            let focused = self.view.focused == Some(*id);
+            let accent = match focused {
+                true => &self.colors.pane_active_border,
+                false => &self.colors.pane_border,
+            };
+            let look = PaneLook {
+                exit,
+                focused,
+                dim: self.dim_inactive && !focused,
+                number: if numbered {
+                    i.to_string()
+                } else {
+                    String::new()
+                },
+                accent: accent.fg.clone(),
+            };
+            if i < self.panes.len() {
+                self.panes.get_mut(i).unwrap().update(*id, *rect, look);
+            } else {
+                self.panes.push_back(PaneView::new(*id, *rect, look));
+            }
+        }
+        let has_panes = !arranged.is_empty();
+        if self.has_panes.copy_value() != has_panes {
+            self.has_panes.set(has_panes);
         }
     }
 
-    pub fn request_new_pty(&mut self) {
-        self.command_queue.vd.push_front(UserRequestType::NewPTY);
+    fn notify(&mut self, notice: Notice) {
+        self.log.push(notice.clone());
+        self.fresh.push(notice);
+    }
+
+    /// Fill `messages` with the newest notices that fit in `rows`, oldest
+    /// at the top.
+    fn fill_messages(&mut self, rows: usize) {
+        let mut lines: Vec<String> = self
+            .log
+            .iter()
+            .rev()
+            .take(rows)
+            .map(|notice| {
+                let level = match notice.level {
+                    Level::Info => "info",
+                    Level::Warn => "warn",
+                    Level::Error => "error",
+                };
+                let time = Clock::at(notice.at);
+                format!("{} {level:<5} {}", time.time(), notice.text)
+            })
+            .collect();
+        lines.reverse();
+        while !self.messages.is_empty() {
+            self.messages.pop_back();
+        }
+        for line in lines {
+            self.messages.push_back(line);
+        }
+    }
+
+    /// Take in what the server sent since the last tick. Returns whether
+    /// the session is over.
+    fn receive(&mut self) -> bool {
+        while let Some(message) = self.connection.try_recv() {
+            match message {
+                ServerMessage::View(view) => {
+                    let shown: Vec<PaneId> = view.panes.iter().map(|(id, _)| *id).collect();
+                    self.screens.retain(&shown);
+                    self.view = *view;
+                }
+                ServerMessage::Screen {
+                    pane,
+                    rows,
+                    cols,
+                    dump,
+                } => {
+                    let mut terminal = Terminal::new(rows as usize, cols as usize);
+                    terminal.advance(&dump);
+                    self.screens.insert(pane, Arc::new(Mutex::new(terminal)));
+                }
+                ServerMessage::Notice(notice) => self.notify(notice),
+                // what happened before this client attached, for the log
+                // but not the statusfeed
+                ServerMessage::History(notices) => {
+                    for notice in notices {
+                        self.log.push(notice);
+                    }
+                }
+                ServerMessage::Clipboard(text) => set_clipboard(&text),
+                ServerMessage::Info(_) => {}
+                ServerMessage::Exit => return true,
+            }
+        }
+        false
     }
 }
 
-struct UIMain {}
+struct UIMain {
+    // what the statusline was last sent, to only send changes
+    tabs: Vec<Tab>,
+    prompt: Option<String>,
+    armed: bool,
+    scroll: Option<(usize, usize)>,
+    vars: Vars,
+    prefix: PrefixKey,
+    colors: Colors,
+    segments: Vec<Segment>,
+    // the same for the statusfeed
+    search: Option<String>,
+    notice_seconds: u64,
+    // what the borders canvas has on it, while there is one
+    borders: Option<(Vec<border::Line>, Colors)>,
+    // said once the ui is gone, like why it went
+    farewell: Arc<Mutex<String>>,
+}
 
 impl UIMain {
-    fn new() -> Self {
-        Self {}
+    fn new(farewell: Arc<Mutex<String>>, settings: &Settings) -> Self {
+        Self {
+            tabs: vec![],
+            prompt: None,
+            armed: false,
+            scroll: None,
+            vars: Vars::default(),
+            prefix: settings.prefix,
+            colors: settings.config.colors.clone(),
+            segments: settings.segments.clone(),
+            search: None,
+            notice_seconds: settings.config.notice_seconds,
+            borders: None,
+            farewell,
+        }
+    }
+
+    // Anathema only picks up the first of several changes to a value
+    // within a frame, so the statusline is sent at most one of each
+    // message per tick, and only when something changed.
+    fn update_statusline(
+        &mut self,
+        state: &UIMainState,
+        context: &mut Context<'_, '_, UIMainState>,
+    ) {
+        // a reloaded config redraws the tabs too, what else changed
+        // waits for the next tick
+        let prefix = state.prefix.key();
+        if prefix != self.prefix || state.colors != self.colors || state.segments != self.segments {
+            self.prefix = prefix;
+            self.colors = state.colors.clone();
+            self.segments = state.segments.clone();
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Theme(Box::new(StatusTheme {
+                prefix,
+                colors: self.colors.clone(),
+                segments: self.segments.clone(),
+            })));
+            return;
+        }
+
+        let tabs = &state.view.tabs;
+        if *tabs != self.tabs {
+            self.tabs = tabs.clone();
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Tabs(tabs.clone()));
+        }
+
+        // searches are typed into the statusfeed instead
+        let prompt = state
+            .prompt
+            .as_ref()
+            .filter(|p| !matches!(p.kind(), PromptKind::Search { .. }))
+            .map(|p| p.display());
+        if prompt != self.prompt {
+            self.prompt = prompt.clone();
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Prompt(prompt));
+        }
+
+        let armed = state.prefix.is_armed();
+        if armed != self.armed {
+            self.armed = armed;
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Prefix(armed));
+        }
+
+        let scroll = state.view.scroll;
+        if scroll != self.scroll {
+            self.scroll = scroll;
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Scroll(scroll));
+        }
+
+        let info = state.view.info.clone().unwrap_or_default();
+        let mode = if armed {
+            "prefix"
+        } else if scroll.is_some() {
+            "copy"
+        } else {
+            ""
+        };
+        let vars = Vars {
+            session: state.session.clone(),
+            window: state.view.active,
+            pane: state.focused().map_or(String::new(), |id| id.to_string()),
+            command: info.command,
+            cwd: info.cwd,
+            status: info.status,
+            exit: info.exit.unwrap_or_default(),
+            mode,
+        };
+        if vars != self.vars {
+            self.vars = vars.clone();
+            let statusline = context.components.by_name("statusline");
+            statusline.send(StatusLineMessage::Vars(Box::new(vars)));
+        }
+    }
+
+    /// Draw the lines between the panes again if they moved, or the focus
+    /// or their colours changed.
+    fn update_borders(&mut self, state: &UIMainState, interior: &mut Children<'_, '_>) {
+        let lines = border::lines(&state.view.panes, state.focused(), state.area);
+        let wanted = (lines, state.colors.clone());
+        let mut found = false;
+        interior
+            .elements()
+            .by_attribute("id", "borders")
+            .first(|e, _| {
+                found = true;
+                if self.borders.as_ref() != Some(&wanted) {
+                    paint_borders(e.to::<TermCanvas>(), &wanted.0, &wanted.1);
+                    self.borders = Some(wanted.clone());
+                }
+            });
+        // the canvas goes with the panes, a new one starts out blank
+        if !found {
+            self.borders = None;
+        }
+    }
+
+    /// Pass the statusfeed what it shows: the search being typed or gone
+    /// through, otherwise the latest notice.
+    fn update_statusfeed(
+        &mut self,
+        state: &mut UIMainState,
+        context: &mut Context<'_, '_, UIMainState>,
+    ) {
+        // the statusline takes note of the colours, after this
+        if state.colors != self.colors || state.notice_seconds != self.notice_seconds {
+            self.notice_seconds = state.notice_seconds;
+            let statusfeed = context.components.by_name("statusfeed");
+            statusfeed.send(StatusFeedMessage::Theme {
+                colors: Box::new(state.colors.clone()),
+                seconds: state.notice_seconds,
+            });
+        }
+
+        let summary = state.view.search.as_ref().map(|s| s.summary());
+        let search = match (&state.prompt, &state.view.search) {
+            (Some(prompt), _) if matches!(prompt.kind(), PromptKind::Search { .. }) => Some(
+                format!("{} {}", prompt.display(), summary.unwrap_or_default()),
+            ),
+            (_, Some(status)) => Some(format!(
+                "search: {} {}",
+                status.query.text,
+                summary.unwrap_or_default()
+            )),
+            _ => None,
+        };
+        if search != self.search {
+            self.search = search.clone();
+            let statusfeed = context.components.by_name("statusfeed");
+            statusfeed.send(StatusFeedMessage::Search(search));
+        }
+
+        // all of them are in the log, only the last one needs showing
+        let latest = state.fresh.pop();
+        state.fresh.clear();
+        if let Some(notice) = latest {
+            let statusfeed = context.components.by_name("statusfeed");
+            statusfeed.send(StatusFeedMessage::Notice(notice));
+            if state.show_messages.copy_value() {
+                state.fill_messages(state.area.0.saturating_sub(2) as usize);
+            }
+        }
     }
 }
 
@@ -103,23 +666,67 @@ impl Component for UIMain {
         &mut self,
         state: &mut Self::State,
         mut interior: Children<'_, '_>,
-        _context: Context<'_, '_, Self::State>,
+        mut context: Context<'_, '_, Self::State>,
         _dt: Duration,
     ) {
-        // process user command request queue,
-        // haphazardly simply taking the back one
-        if let Some(cmd) = state.command_queue.vd.pop_back() {
-            match cmd {
-                UserRequestType::NewPTY => state.request_new_pty(),
-            };
+        state.prefix.expire();
+        if state
+            .numbers_at
+            .is_some_and(|at| clock::since(at) >= NUMBERS_SHOWN)
+        {
+            state.numbers_at = None;
+        }
+
+        if state.detach {
+            *self.farewell.lock().unwrap() = "detached".to_string();
+            context.stop_runtime();
+            return;
+        }
+
+        // hand user requests to the server, oldest first
+        for cmd in state.command_queue.drain_batch(COMMANDS_PER_TICK) {
+            if state.connection.send(&ClientMessage::Command(cmd)).is_err() {
+                // the server is gone, `receive` hears about it next
+                break;
+            }
+        }
+
+        if state.receive() {
+            *self.farewell.lock().unwrap() = "exited".to_string();
+            context.stop_runtime();
+            return;
         }
+        self.update_statusfeed(state, &mut context);
+        self.update_statusline(state, &mut context);
+
+        // the server splits whatever room there is between the panes
+        let mut elements = interior.elements();
+        let mut area = state.area;
+        elements.by_attribute("id", "panes").first(|e, _| {
+            let sz = e.size();
+            area = (sz.height, sz.width);
+            let pos = e.get_pos();
+            state.area_at = (pos.x.max(0) as u16, pos.y.max(0) as u16);
+        });
+        if area != state.area {
+            let (rows, cols) = area;
+            if state
+                .connection
+                .send(&ClientMessage::Resize { rows, cols })
+                .is_ok()
+            {
+                state.area = area;
+            }
+        }
+        state.sync_views();
+        self.update_borders(state, &mut interior);
 
         // not strictly necessary, this was prototype code
         // just to manage global fps of canvasfx components
         let mut elements = interior.elements();
         elements
             .by_attribute("id", "canvasfx")
-            .each(|_e, attributes| {
+            .each(|_, attributes| {
                 attributes.set("fps", state.fps.copy_value());
             });
     }
@@ -129,57 +736,585 @@ impl Component for UIMain {
         key: KeyEvent,
         state: &mut Self::State,
         mut _interior: Children<'_, '_>,
-        mut context: Context<'_, '_, Self::State>,
+        mut _context: Context<'_, '_, Self::State>,
     ) {
-        match key.code {
-            KeyCode::Char('j') => {
-                let current = *state.fps.to_mut();
-                if current > 1 {
-                    *state.fps.to_mut() -= 1;
+        // an open prompt gets every key until it's done
+        if let Some(prompt) = state.prompt.as_mut() {
+            match prompt.key(&key) {
+                PromptEvent::Editing => {}
+                PromptEvent::Edited(kind, input) => match kind {
+                    PromptKind::RenamePane(_) | PromptKind::RenameWindow(_) => {}
+                    PromptKind::Search {
+                        pane,
+                        backward,
+                        regex,
+                    } => {
+                        let query = Query {
+                            text: input,
+                            backward,
+                            regex,
+                        };
+                        let action = CopyAction::Search(query);
+                        state
+                            .command_queue
+                            .push(UserRequestType::Copy { pane, action });
+                    }
+                },
+                PromptEvent::Submit(kind, input) => {
+                    state.command_queue.push(match kind {
+                        PromptKind::RenamePane(pane) => {
+                            UserRequestType::Rename { pane, title: input }
+                        }
+                        PromptKind::RenameWindow(window) => UserRequestType::RenameWindow {
+                            window,
+                            name: input,
+                        },
+                        PromptKind::Search { pane, .. } => UserRequestType::Copy {
+                            pane,
+                            action: CopyAction::SearchDone { keep: true },
+                        },
+                    });
+                    state.prompt = None;
+                }
+                PromptEvent::Cancel(kind) => {
+                    if let PromptKind::Search { pane, .. } = kind {
+                        let action = CopyAction::SearchDone { keep: false };
+                        state
+                            .command_queue
+                            .push(UserRequestType::Copy { pane, action });
+                    }
+                    state.prompt = None;
+                }
+            }
+            return;
+        }
+
+        // with the numbers up, a digit picks a pane and anything else
+        // takes them down
+        if state.numbers_at.take().is_some()
+            && let KeyCode::Char(c @ '0'..='9') = key.code
+            && !key.ctrl
+        {
+            let number = c.to_digit(10).unwrap() as usize;
+            run_command(Command::SelectPaneNumber(number), state);
+            return;
+        }
+
+        match state.prefix.route(&key) {
+            KeyRoute::Armed => {}
+            KeyRoute::Command => run_binding(key, state),
+            // the message log is in front of the panes, so what's typed
+            // goes nowhere until it's closed
+            KeyRoute::Forward if state.show_messages.copy_value() => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                    state.show_messages.set(false);
                 }
             }
-            KeyCode::Char('n') => state.request_new_pty(),
-            KeyCode::Char('q') => context.stop_runtime(),
-            KeyCode::Char('k') => {
-                let current = *state.fps.to_mut();
-                if current < 30 {
-                    *state.fps.to_mut() += 1;
+            // keys copy mode has no use for type into the pane, which
+            // takes it back out of copy mode
+            KeyRoute::Forward if state.view.scroll.is_some() && copy_key(&key, state) => {}
+            KeyRoute::Forward => forward_key(key, state),
+        }
+    }
+
+    fn on_mouse(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut Self::State,
+        mut _interior: Children<'_, '_>,
+        mut _context: Context<'_, '_, Self::State>,
+    ) {
+        // the message log is in front of the panes
+        if state.show_messages.copy_value() {
+            return;
+        }
+        match mouse.state {
+            MouseState::Down(MouseButton::Left) => {
+                // the statusline is above the panes
+                if mouse.y < state.area_at.1 {
+                    if let Some(window) = state.tab_at(mouse.x) {
+                        state
+                            .command_queue
+                            .push(UserRequestType::SelectWindow(window));
+                    }
+                    return;
+                }
+                if let Some((pane, axis)) = state.divider_at(mouse.x, mouse.y) {
+                    let at = along(axis, mouse, state.area_at);
+                    state.drag = Some((pane, axis, at));
+                    return;
+                }
+                // a click picks a pane, and still goes to it if it
+                // wants clicks
+                match state.pane_at(mouse.x, mouse.y) {
+                    Some((pane, _)) if state.focused() != Some(pane) => {
+                        state.command_queue.push(UserRequestType::Focus(pane));
+                    }
+                    _ => {}
                 }
             }
+            MouseState::Drag(MouseButton::Left) if state.drag.is_some() => {
+                drag_divider(mouse, state);
+                return;
+            }
+            MouseState::Up(MouseButton::Left) if state.drag.take().is_some() => return,
+            MouseState::ScrollUp | MouseState::ScrollDown if scroll_wheel(mouse, state) => {
+                return;
+            }
             _ => {}
         }
+        forward_mouse(mouse, state);
+    }
+}
+
+/// The multiplexer's own commands, reached through the prefix key.
+fn run_binding(key: KeyEvent, state: &mut UIMainState) {
+    if let Some(command) = state.bindings.get(&key) {
+        run_command(command.clone(), state);
+    }
+}
+
+/// Carry out a bound command, asking the server for what it does.
+fn run_command(command: Command, state: &mut UIMainState) {
+    // the server reads the config for itself, the client's share of it
+    // is taken up here. A broken one is left alone on both sides.
+    if command == Command::ReloadConfig {
+        match config::load() {
+            Ok(settings) => state.reconfigure(settings),
+            Err(err) => {
+                state.notify(Notice::error(err.to_string()));
+                return;
+            }
+        }
+    }
+    if let Some(request) = command.request(state.focused(), state.view.active) {
+        state.command_queue.push(request);
+        return;
+    }
+    match command {
+        Command::NextPane => {
+            if let Some(id) = state.next_pane() {
+                state.command_queue.push(UserRequestType::Focus(id));
+            }
+        }
+        Command::SelectPane(direction) => {
+            if let Some(id) = state.pane_towards(direction) {
+                state.command_queue.push(UserRequestType::Focus(id));
+            }
+        }
+        Command::SelectPaneNumber(number) => match state.view.panes.get(number) {
+            Some((id, _)) => state.command_queue.push(UserRequestType::Focus(*id)),
+            None => state.notify(Notice::error(format!("no pane {number}"))),
+        },
+        Command::DisplayPanes => state.numbers_at = Some(clock::now()),
+        Command::RenamePane(None) => {
+            if let Some(pane) = state.focused() {
+                let kind = PromptKind::RenamePane(pane);
+                state.prompt = Some(Prompt::new(kind, "rename pane", String::new()));
+            }
+        }
+        Command::RenameWindow(None) => {
+            let index = state.view.active;
+            let name = state.view.name.clone().unwrap_or_default();
+            let kind = PromptKind::RenameWindow(index);
+            state.prompt = Some(Prompt::new(kind, "rename window", name));
+        }
+        Command::CopyMode { page_up: true } => {
+            if let Some(pane) = state.focused() {
+                let on = UserRequestType::CopyMode { pane, on: true };
+                state.command_queue.push(on);
+                let action = CopyAction::Move(Motion::PageUp);
+                state
+                    .command_queue
+                    .push(UserRequestType::Copy { pane, action });
+            }
+        }
+        Command::ShowMessages => {
+            let open = !state.show_messages.copy_value();
+            if open {
+                state.fill_messages(state.area.0.saturating_sub(2) as usize);
+            }
+            state.show_messages.set(open);
+        }
+        Command::Detach => state.detach = true,
+        Command::Fps(delta) => {
+            let fps = (state.fps.copy_value() + delta).clamp(1, state.max_fps);
+            state.fps.set(fps);
+        }
+        // the rest are the server's, and need a focused pane to act on
+        _ => {}
+    }
+}
+
+/// Hand a key to the focused pane's copy mode. Returns false for keys
+/// copy mode doesn't use.
+fn copy_key(key: &KeyEvent, state: &mut UIMainState) -> bool {
+    let Some(pane) = state.focused() else {
+        return false;
+    };
+    if let Some((backward, regex)) = state.mode_keys.search(key) {
+        let kind = PromptKind::Search {
+            pane,
+            backward,
+            regex,
+        };
+        let label = if backward { "search up" } else { "search down" };
+        state.prompt = Some(Prompt::new(kind, label, String::new()));
+        return true;
+    }
+    let Some(action) = state.mode_keys.action(key) else {
+        return false;
+    };
+    state
+        .command_queue
+        .push(UserRequestType::Copy { pane, action });
+    true
+}
+
+/// Put `text` on the clipboard of the terminal fizzle runs in, through
+/// OSC 52. Terminals that don't do that ignore it.
+fn set_clipboard(text: &str) {
+    use std::io::Write;
+    let mut stdout = std::io::stdout();
+    _ = stdout
+        .write_all(&copy::osc52(text))
+        .and_then(|_| stdout.flush());
+}
+
+/// Type the key into the focused pane, if there is one.
+fn forward_key(key: KeyEvent, state: &mut UIMainState) {
+    let Some(pane) = state.focused() else {
+        return;
+    };
+    let modes = match state.screens.get(pane) {
+        Some(terminal) => terminal.lock().unwrap().screen().modes(),
+        None => Modes::default(),
+    };
+    if let Some(bytes) = input::encode_key(&key, &modes) {
+        state
+            .command_queue
+            .push(UserRequestType::SendKeys { pane, bytes });
+    }
+}
+
+/// Report a mouse event to the program in the pane under it, if that
+/// program asked for mouse events. A pane in copy mode shows its
+/// scrollback rather than the program, so that gets none.
+fn forward_mouse(mouse: MouseEvent, state: &mut UIMainState) {
+    let Some((pane, at)) = state.pane_at(mouse.x, mouse.y) else {
+        return;
+    };
+    if state.view.scroll.is_some() && state.focused() == Some(pane) {
+        return;
+    }
+    let Some(terminal) = state.screens.get(pane) else {
+        return;
+    };
+    let modes = terminal.lock().unwrap().screen().modes();
+    if let Some(bytes) = input::encode_mouse(&mouse, at, &modes) {
+        state
+            .command_queue
+            .push(UserRequestType::SendKeys { pane, bytes });
+    }
+}
+
+/// Where `mouse` is along `axis`, as a column or row of the panes' area
+/// starting at `area_at`.
+fn along(axis: Axis, mouse: MouseEvent, area_at: (u16, u16)) -> u16 {
+    match axis {
+        Axis::Horizontal => mouse.x.saturating_sub(area_at.0),
+        Axis::Vertical => mouse.y.saturating_sub(area_at.1),
+    }
+}
+
+/// Follow the mouse with the divider being dragged.
+fn drag_divider(mouse: MouseEvent, state: &mut UIMainState) {
+    let Some((pane, axis, at)) = state.drag else {
+        return;
+    };
+    let to = along(axis, mouse, state.area_at);
+    if to != at {
+        state.drag = Some((pane, axis, to));
+        state
+            .command_queue
+            .push(UserRequestType::MoveDivider { pane, axis, to });
+    }
+}
+
+/// Scroll the pane under the wheel through its scrollback, in copy mode,
+/// unless its program asked for the mouse. Whether the wheel was taken.
+fn scroll_wheel(mouse: MouseEvent, state: &mut UIMainState) -> bool {
+    let Some((pane, _)) = state.pane_at(mouse.x, mouse.y) else {
+        return false;
+    };
+    let Some(terminal) = state.screens.get(pane) else {
+        return false;
+    };
+    if terminal.lock().unwrap().screen().modes().mouse != MouseTracking::Off {
+        return false;
+    }
+    let focused = state.focused() == Some(pane);
+    let scroll = state.view.scroll.filter(|_| focused);
+    let queue = &mut state.command_queue;
+    match (mouse.state, scroll) {
+        (MouseState::ScrollUp, scroll) => {
+            if !focused {
+                queue.push(UserRequestType::Focus(pane));
+            }
+            if scroll.is_none() {
+                queue.push(UserRequestType::CopyMode { pane, on: true });
+            }
+            queue.push(UserRequestType::Scroll {
+                pane,
+                lines: WHEEL_LINES,
+            });
+        }
+        // back at the bottom is back to watching the pane
+        (_, Some((offset, _))) if offset <= WHEEL_LINES as usize => {
+            queue.push(UserRequestType::CopyMode { pane, on: false });
+        }
+        (_, Some(_)) => queue.push(UserRequestType::Scroll {
+            pane,
+            lines: -WHEEL_LINES,
+        }),
+        (_, None) => {}
     }
+    true
+}
+
+/// Things the statusline gets told about by the main component.
+enum StatusLineMessage {
+    /// The prefix key was pressed and is waiting for a command key.
+    Prefix(bool),
+    Tabs(Vec<Tab>),
+    /// What an open prompt reads, `None` once it's closed.
+    Prompt(Option<String>),
+    /// `(offset, lines)` of the focused pane's scrollback in copy mode.
+    Scroll(Option<(usize, usize)>),
+    /// What the segments are filled in from changed.
+    Vars(Box<Vars>),
+    /// The config was read again.
+    Theme(Box<StatusTheme>),
+}
+
+/// The parts of the config the statusline draws with.
+struct StatusTheme {
+    prefix: PrefixKey,
+    colors: Colors,
+    segments: Vec<Segment>,
+}
+
+#[derive(State)]
+struct TabView {
+    label: Value<String>,
+    foreground: Value<String>,
+    background: Value<String>,
 }
 
 #[derive(State)]
-struct StatusLineState {}
+struct SegmentView {
+    text: Value<String>,
+    foreground: Value<String>,
+    background: Value<String>,
+}
+
+#[derive(State)]
+struct StatusLineState {
+    prefix: Value<String>,
+    prefix_fg: Value<String>,
+    prefix_bg: Value<String>,
+    tabs: Value<List<TabView>>,
+    prompt: Value<String>,
+    scroll: Value<String>,
+    scroll_fg: Value<String>,
+    scroll_bg: Value<String>,
+    segments: Value<List<SegmentView>>,
+    // kept to draw the prefix and tabs again in other colors
+    #[anathema(ignore)]
+    colors: Colors,
+    #[anathema(ignore)]
+    armed: bool,
+    #[anathema(ignore)]
+    last_tabs: Vec<Tab>,
+    // the segments' formats and what each was last drawn as
+    #[anathema(ignore)]
+    formats: Vec<Segment>,
+    #[anathema(ignore)]
+    rendered: Vec<String>,
+}
 
 impl StatusLineState {
-    fn new() -> Self {
-        Self {}
+    fn new(prefix: PrefixKey, colors: Colors, segments: Vec<Segment>) -> Self {
+        let mut state = Self {
+            prefix: format!(" {prefix} ").into(),
+            prefix_fg: colors.prefix.fg.clone().into(),
+            prefix_bg: colors.prefix.bg.clone().into(),
+            tabs: List::empty().into(),
+            prompt: String::new().into(),
+            scroll: String::new().into(),
+            scroll_fg: colors.copy_mode.fg.clone().into(),
+            scroll_bg: colors.copy_mode.bg.clone().into(),
+            segments: List::empty().into(),
+            colors,
+            armed: false,
+            last_tabs: vec![],
+            formats: segments,
+            rendered: vec![],
+        };
+        state.paint_segments(&Vars::default(), &Clock::now());
+        state
+    }
+
+    fn paint_prefix(&mut self) {
+        let pair = match self.armed {
+            true => &self.colors.prefix_armed,
+            false => &self.colors.prefix,
+        };
+        self.prefix_fg.set(pair.fg.clone());
+        self.prefix_bg.set(pair.bg.clone());
+    }
+
+    fn paint_tabs(&mut self) {
+        while !self.tabs.is_empty() {
+            self.tabs.pop_back();
+        }
+        for tab in &self.last_tabs {
+            let pair = match tab.theme {
+                0 => &self.colors.tab,
+                1 => &self.colors.tab_active,
+                _ => &self.colors.tab_alert,
+            };
+            self.tabs.push_back(TabView {
+                label: tab.label.clone().into(),
+                foreground: pair.fg.clone().into(),
+                background: pair.bg.clone().into(),
+            });
+        }
+    }
+
+    /// Draw every segment from scratch, for when the formats changed.
+    fn paint_segments(&mut self, vars: &Vars, clock: &Clock) {
+        while !self.segments.is_empty() {
+            self.segments.pop_back();
+        }
+        self.rendered.clear();
+        for segment in &self.formats {
+            let text = segment.format.render(vars, clock);
+            self.segments.push_back(SegmentView {
+                text: text.clone().into(),
+                foreground: segment.fg.clone().into(),
+                background: segment.bg.clone().into(),
+            });
+            self.rendered.push(text);
+        }
+    }
+
+    /// Fill the segments in again, only touching the ones that changed.
+    fn update_segments(&mut self, vars: &Vars, clock: &Clock) {
+        for (i, segment) in self.formats.iter().enumerate() {
+            let text = segment.format.render(vars, clock);
+            if self.rendered[i] == text {
+                continue;
+            }
+            if let Some(mut view) = self.segments.get_mut(i) {
+                view.text.set(text.clone());
+            }
+            self.rendered[i] = text;
+        }
+    }
+
+    fn uses_clock(&self) -> bool {
+        self.formats
+            .iter()
+            .any(|segment| segment.format.uses_clock())
     }
 }
 
-struct StatusLine {}
+struct StatusLine {
+    vars: Vars,
+    // the segments need filling in again, and the second they last were
+    dirty: bool,
+    second: u64,
+}
 
 impl StatusLine {
     fn new() -> Self {
-        Self {}
+        Self {
+            vars: Vars::default(),
+            dirty: true,
+            second: 0,
+        }
     }
 }
 
 impl Component for StatusLine {
-    type Message = ();
+    type Message = StatusLineMessage;
     type State = StatusLineState;
 
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        mut _interior: Children<'_, '_>,
+        _context: Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            StatusLineMessage::Prefix(armed) => {
+                state.armed = armed;
+                state.paint_prefix();
+            }
+            StatusLineMessage::Tabs(tabs) => {
+                state.last_tabs = tabs;
+                state.paint_tabs();
+            }
+            StatusLineMessage::Prompt(text) => state.prompt.set(text.unwrap_or_default()),
+            StatusLineMessage::Scroll(position) => {
+                let text = match position {
+                    Some((offset, lines)) => format!(" [{offset}/{lines}] "),
+                    None => String::new(),
+                };
+                state.scroll.set(text);
+            }
+            StatusLineMessage::Vars(vars) => {
+                self.vars = *vars;
+                self.dirty = true;
+            }
+            StatusLineMessage::Theme(theme) => {
+                let StatusTheme {
+                    prefix,
+                    colors,
+                    segments,
+                } = *theme;
+                state.prefix.set(format!(" {prefix} "));
+                state.scroll_fg.set(colors.copy_mode.fg.clone());
+                state.scroll_bg.set(colors.copy_mode.bg.clone());
+                state.colors = colors;
+                state.formats = segments;
+                state.paint_prefix();
+                state.paint_tabs();
+                state.paint_segments(&self.vars, &Clock::now());
+            }
+        }
+    }
+
+    // Segments are only filled in again when what they show changed or,
+    // for the ones with a clock, once a second, and only the ones that
+    // came out different are set, so the rest of the line isn't redrawn.
     fn on_tick(
         &mut self,
-        _state: &mut Self::State,
+        state: &mut Self::State,
         mut _interior: Children<'_, '_>,
         _context: Context<'_, '_, Self::State>,
         _dt: Duration,
     ) {
-        // unused for now
+        let second = clock::current_second();
+        let ticked = second != self.second && state.uses_clock();
+        if !self.dirty && !ticked {
+            return;
+        }
+        self.dirty = false;
+        self.second = second;
+        state.update_segments(&self.vars, &Clock::now());
     }
 
     fn on_key(
@@ -193,35 +1328,125 @@ impl Component for StatusLine {
     }
 }
 
+/// What the statusfeed is told.
+enum StatusFeedMessage {
+    Notice(Notice),
+    /// The search being typed or gone through, shown over any notice.
+    Search(Option<String>),
+    Theme {
+        colors: Box<Colors>,
+        seconds: u64,
+    },
+}
+
 #[derive(State)]
-struct StatusFeedState {}
+struct StatusFeedState {
+    readout: Value<String>,
+    foreground: Value<String>,
+    background: Value<String>,
+}
 
 impl StatusFeedState {
-    fn new() -> Self {
-        Self {}
+    fn new(colors: &Colors) -> Self {
+        Self {
+            readout: String::new().into(),
+            foreground: colors.statusfeed.fg.clone().into(),
+            background: colors.statusfeed.bg.clone().into(),
+        }
     }
 }
 
-struct StatusFeed {}
+struct StatusFeed {
+    colors: Colors,
+    // how long a notice stays up, 0 for until the next one
+    seconds: u64,
+    notice: Option<(Notice, Instant)>,
+    search: Option<String>,
+    // what the state was last set to: text, foreground and background
+    shown: (String, String, String),
+}
 
 impl StatusFeed {
-    fn new() -> Self {
-        Self {}
+    fn new(colors: Colors, seconds: u64) -> Self {
+        let shown = (
+            String::new(),
+            colors.statusfeed.fg.clone(),
+            colors.statusfeed.bg.clone(),
+        );
+        Self {
+            colors,
+            seconds,
+            notice: None,
+            search: None,
+            shown,
+        }
+    }
+
+    /// The text and colours the statusfeed should have now.
+    fn wanted(&self) -> (String, String, String) {
+        let (text, pair) = match (&self.search, &self.notice) {
+            (Some(search), _) => (search.clone(), &self.colors.statusfeed),
+            (None, Some((notice, _))) => {
+                let pair = match notice.level {
+                    Level::Info => &self.colors.notice_info,
+                    Level::Warn => &self.colors.notice_warn,
+                    Level::Error => &self.colors.notice_error,
+                };
+                (notice.text.clone(), pair)
+            }
+            (None, None) => (String::new(), &self.colors.statusfeed),
+        };
+        (text, pair.fg.clone(), pair.bg.clone())
     }
 }
 
 impl Component for StatusFeed {
-    type Message = ();
+    type Message = StatusFeedMessage;
     type State = StatusFeedState;
 
-    fn on_tick(
+    // what's shown is worked out in on_tick, so messages that come in
+    // together don't trip over each other
+    fn on_message(
         &mut self,
+        message: Self::Message,
         _state: &mut Self::State,
         mut _interior: Children<'_, '_>,
         _context: Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            StatusFeedMessage::Notice(notice) => self.notice = Some((notice, clock::now())),
+            StatusFeedMessage::Search(search) => self.search = search,
+            StatusFeedMessage::Theme { colors, seconds } => {
+                self.colors = *colors;
+                self.seconds = seconds;
+            }
+        }
+    }
+
+    fn on_tick(
+        &mut self,
+        state: &mut Self::State,
+        mut _interior: Children<'_, '_>,
+        _context: Context<'_, '_, Self::State>,
         _dt: Duration,
     ) {
-        // unused for now
+        if let Some((_, since)) = &self.notice
+            && self.seconds > 0
+            && clock::since(*since) >= Duration::from_secs(self.seconds)
+        {
+            self.notice = None;
+        }
+        let (text, fg, bg) = self.wanted();
+        if text != self.shown.0 {
+            state.readout.set(text.clone());
+        }
+        if fg != self.shown.1 {
+            state.foreground.set(fg.clone());
+        }
+        if bg != self.shown.2 {
+            state.background.set(bg.clone());
+        }
+        self.shown = (text, fg, bg);
     }
 
     fn on_key(
@@ -245,6 +1470,7 @@ impl CanvasFXState {
 }
 
 struct CanvasFX {
+    screens: ScreenRegistry,
     app_start: Instant,
     time_secs: f64,     // this value is only updated on an animation tick
     anim_tick: usize,   // this value goes up every n seconds
@@ -252,9 +1478,10 @@ struct CanvasFX {
 }
 
 impl CanvasFX {
-    fn new() -> Self {
+    fn new(screens: ScreenRegistry) -> Self {
         Self {
-            app_start: Instant::now(),
+            screens,
+            app_start: clock::now(),
             time_secs: 0.0f64,
             anim_tick: 0usize,
             anim_tick_per: 1.0 / 24.0,
@@ -283,6 +1510,82 @@ pub fn safe_neighbor(sz: (u16, u16), coord: (u16, u16), delta: (i8, i8)) -> (u16
     (test_coord.0 as u16, test_coord.1 as u16)
 }
 
+/// Draw the lines between panes, the ones around the focused pane in
+/// colours of their own.
+fn paint_borders(canvas: &mut TermCanvas, lines: &[border::Line], colors: &Colors) {
+    let style = |pair: &ColorPair| {
+        let mut style = Style::new();
+        style.fg = pair.fg.parse().ok();
+        style.bg = pair.bg.parse().ok();
+        style
+    };
+    let (plain, active) = (
+        style(&colors.pane_border),
+        style(&colors.pane_active_border),
+    );
+    canvas.clear();
+    for line in lines {
+        let style = if line.active { active } else { plain };
+        canvas.put(line.c, style, (line.x, line.y));
+    }
+}
+
+/// Put `number` in the middle of the canvas, in `accent`, for
+/// `display-panes`.
+fn paint_number(canvas: &mut TermCanvas, number: &str, accent: Option<Color>, (w, h): (u16, u16)) {
+    let label = format!(" {number} ");
+    let width = label.width() as u16;
+    let (left, top) = (w.saturating_sub(width) / 2, h / 2);
+    let mut style = Style::new();
+    style.fg = accent;
+    style.attributes |= Attributes::REVERSED | Attributes::BOLD;
+    for (x, c) in (left..w).zip(label.chars()) {
+        canvas.put(c, style, (x, top));
+    }
+}
+
+/// A box in the middle of a dead pane's screen saying how its process
+/// ended and what can be done about it.
+fn paint_exit(canvas: &mut TermCanvas, exit: &str, (w, h): (u16, u16)) {
+    let lines = [
+        exit,
+        "respawn-pane to start it again, close-pane to close it",
+    ];
+    let inner = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+    let (width, height) = ((inner + 2).min(w), (lines.len() as u16 + 2).min(h));
+    let (left, top) = ((w - width) / 2, (h - height) / 2);
+    let mut style = Style::new();
+    style.attributes |= Attributes::REVERSED;
+    for y in 0..height {
+        let row: Vec<char> = match y {
+            0 => format!("┌{}┐", "─".repeat(inner as usize))
+                .chars()
+                .collect(),
+            _ if y == height - 1 => format!("└{}┘", "─".repeat(inner as usize))
+                .chars()
+                .collect(),
+            _ => {
+                let line = lines[y as usize - 1];
+                let pad = " ".repeat(inner as usize - 2 - line.width());
+                format!("│ {line}{pad} │").chars().collect()
+            }
+        };
+        let mut x = 0;
+        for c in row {
+            let w = c.width().unwrap_or(0) as u16;
+            if x + w > width {
+                break;
+            }
+            // the box is for plain text, a stray mark has nothing to go on
+            if w == 0 {
+                continue;
+            }
+            canvas.put(c, style, (left + x, top + y));
+            x += w;
+        }
+    }
+}
+
 impl Component for CanvasFX {
     type Message = ();
     type State = CanvasFXState;
@@ -294,7 +1597,7 @@ impl Component for CanvasFX {
         _context: Context<'_, '_, Self::State>,
         _dt: Duration,
     ) {
-        let time_now_secs = self.app_start.elapsed().as_secs_f64();
+        let time_now_secs = clock::since(self.app_start).as_secs_f64();
 
         if self.time_secs + self.anim_tick_per > time_now_secs {
             return;
@@ -315,9 +1618,29 @@ impl Component for CanvasFX {
             // let w = eib.to.x - eib.from.x;
             // let h = eib.to.y - eib.from.y;
 
-            let canvas = e.to::<Canvas>();
+            let canvas = e.to::<TermCanvas>();
             let style = Style::new();
 
+            // a pane to show takes over the whole canvas, no fizz
+            let pane = a.get("pane").and_then(|v| v.as_int());
+            if let Some(terminal) = pane.and_then(|id| self.screens.get(PaneId::from_raw(id as u32))) {
+                // templates from before panes had focus leave it out
+                let look = Look {
+                    cursor: a.get("focused").and_then(|v| v.as_bool()).unwrap_or(true),
+                    dim: a.get("dim").and_then(|v| v.as_bool()).unwrap_or(false),
+                };
+                canvas.clear();
+                render::paint_with(terminal.lock().unwrap().screen(), canvas, look);
+                if let Some(exit) = a.get("exit").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+                    paint_exit(canvas, exit, (w, h));
+                }
+                if let Some(number) = a.get("number").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+                    let accent = a.get("accent").and_then(|v| v.as_str()).and_then(|s| s.parse().ok());
+                    paint_number(canvas, number, accent, (w, h));
+                }
+                return;
+            }
+
             // let output = a
             //     .get("output")
             //     .unwrap()
@@ -339,7 +1662,7 @@ impl Component for CanvasFX {
                         continue;
                     }
                     canvas.put(char, style, (x_index, y_index));
-                    x_index += 1;
+                    x_index += char.width().unwrap_or(0) as u16;
                 }
             }
 
@@ -392,112 +1715,615 @@ impl Component for CanvasFX {
 }
 
 fn main() -> anyhow::Result<()> {
-    smol::block_on(async {
-        /*
-        let pty_system = native_pty_system();
-
-        let pair = pty_system.openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
-
-        let subprocess_cmd: Vec<OsString> = vec![
-            "ls".to_string().into(),
-            "--color=auto".to_string().into(),
-            "-lh".to_string().into(),
-        ];
-        let mut cmd = CommandBuilder::from_argv(subprocess_cmd);
-        if let Ok(cwd) = std::env::current_dir() {
-            cmd.cwd(cwd);
-        }
-
-        let slave = pair.slave;
+    match Action::parse(std::env::args().skip(1))? {
+        Action::New(name) => {
+            let name = match name {
+                Some(name) => name,
+                None => unused_name()?,
+            };
+            // a broken config is better heard about before the server
+            // starts without anyone to tell
+            let settings = load_settings()?;
+            server::spawn(&name)?;
+            attach(&name, settings)
+        }
+        Action::Attach(target) => {
+            let settings = load_settings()?;
+            attach(&pick_session(target)?, settings)
+        }
+        Action::List => list_sessions(),
+        Action::Kill(target) => {
+            let mut connection = Connection::open(&pick_session(target)?)?;
+            connection.send(&ClientMessage::Command(UserRequestType::Quit))?;
+            // it's gone once it says so or hangs up
+            while !matches!(connection.recv(), ServerMessage::Exit) {}
+            Ok(())
+        }
+        Action::Server(name) => server::run(&name),
+        Action::Help => {
+            print!("{USAGE}");
+            Ok(())
+        }
+    }
+}
+
+/// The config and the templates it points at, both checked.
+fn load_settings() -> anyhow::Result<(Settings, Templates)> {
+    let settings = config::load()?;
+    let templates = Templates::new(TEMPLATES, config::templates_dir(&settings.config));
+    templates.check(&[
+        ("main", "ui.aml"),
+        ("statusline", "statusline.aml"),
+        ("statusfeed", "statusfeed.aml"),
+        ("canvasfx", "canvasfx.aml"),
+    ])?;
+    Ok((settings, templates))
+}
+
+/// The session called `name`, or the most recently started one.
+fn pick_session(name: Option<String>) -> anyhow::Result<String> {
+    match name {
+        Some(name) => Ok(name),
+        None => protocol::sessions()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("no sessions")),
+    }
+}
+
+/// The lowest number no running session is named after.
+fn unused_name() -> anyhow::Result<String> {
+    let taken = protocol::sessions()?;
+    let n = (0..).find(|n: &usize| !taken.contains(&n.to_string()));
+    Ok(n.unwrap().to_string())
+}
+
+fn list_sessions() -> anyhow::Result<()> {
+    for name in protocol::sessions()? {
+        let mut connection = Connection::open(&name)?;
+        connection.send(&ClientMessage::Query)?;
+        if let ServerMessage::Info(info) = connection.recv() {
+            let attached = if info.attached > 0 { " (attached)" } else { "" };
+            println!(
+                "{name}: {} windows, {} panes{attached}",
+                info.windows, info.panes
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run the ui for session `name` until it is detached or the session ends.
+fn attach(name: &str, (settings, templates): (Settings, Templates)) -> anyhow::Result<()> {
+    let mut connection = Connection::open(name)?;
+    connection.send(&ClientMessage::Attach)?;
+
+    /*
+    let pty_system = native_pty_system();
+
+    let pair = pty_system.openpty(PtySize {
+        rows: 24,
+        cols: 80,
+        pixel_width: 0,
+        pixel_height: 0,
+    })?;
+
+    let subprocess_cmd: Vec<OsString> = vec![
+        "ls".to_string().into(),
+        "--color=auto".to_string().into(),
+        "-lh".to_string().into(),
+    ];
+    let mut cmd = CommandBuilder::from_argv(subprocess_cmd);
+    if let Ok(cwd) = std::env::current_dir() {
+        cmd.cwd(cwd);
+    }
+
+    let slave = pair.slave;
+    // NOTE: deadlock avoidance
+    let mut subprocess = smol::unblock(move || slave.spawn_command(cmd)).await?;
+
+    {
         // NOTE: deadlock avoidance
-        let mut subprocess = smol::unblock(move || slave.spawn_command(cmd)).await?;
+        let writer = pair.master.take_writer()?;
 
-        {
-            // NOTE: deadlock avoidance
-            let writer = pair.master.take_writer()?;
+        // Explicitly generate EOF
+        drop(writer);
+    }
 
-            // Explicitly generate EOF
-            drop(writer);
+    println!(
+        "subprocess status: {:?}",
+        smol::unblock(move || subprocess.wait().map_err(|e| anyhow!(": {}", e))).await?
+    );
+
+    let reader = pair.master.try_clone_reader()?;
+
+    // NOTE: take care. only after processes are done
+    drop(pair.master);
+
+    let mut output: String = Default::default();
+    let mut lines = smol::io::BufReader::new(smol::Unblock::new(reader)).lines();
+    while let Some(line) = lines.next().await {
+        let line = line.map_err(|e| anyhow!("problem reading line: {}", e))?;
+        for c in line.escape_debug() {
+            output.push(c);
         }
+        output.push('\n');
+    }
+    */
 
-        println!(
-            "subprocess status: {:?}",
-            smol::unblock(move || subprocess.wait().map_err(|e| anyhow!(": {}", e))).await?
-        );
+    let doc = Document::new("@main");
+
+    let mut backend = {
+        let mut builder = TuiBackend::builder()
+            .enable_alt_screen()
+            .enable_raw_mode()
+            .hide_cursor();
+        if settings.config.mouse {
+            builder = builder.enable_mouse();
+        }
+        let mut inst = builder.finish().unwrap();
+        inst.finalize();
+        inst
+    };
+
+    let farewell = Arc::new(Mutex::new(String::new()));
+    let mut builder = Runtime::builder(doc, &backend);
+    add_components(
+        &mut builder,
+        connection,
+        name,
+        (settings, templates),
+        farewell.clone(),
+    )?;
+
+    let result = builder.finish(&mut backend, |runtime, backend| runtime.run(backend));
+    // the backend has to restore the terminal before there's anything to say
+    drop(backend);
+    match result {
+        // leaving is reported as `Stop`
+        Ok(()) | Err(anathema::runtime::Error::Stop) => {}
+        Err(err) => return Err(anyhow::anyhow!("{err}")),
+    }
 
-        let reader = pair.master.try_clone_reader()?;
+    match farewell.lock().unwrap().as_str() {
+        "" => {}
+        "detached" => println!("[detached (from session {name})]"),
+        other => println!("[{other}]"),
+    }
+    Ok(())
+}
 
-        // NOTE: take care. only after processes are done
-        drop(pair.master);
+/// Give the runtime the ui's components, talking to the server over
+/// `connection`. Why the ui stopped ends up in `farewell`.
+fn add_components(
+    builder: &mut anathema::runtime::Builder<()>,
+    connection: Connection,
+    name: &str,
+    (settings, templates): (Settings, Templates),
+    farewell: Arc<Mutex<String>>,
+) -> anyhow::Result<()> {
+    let screens = ScreenRegistry::default();
+    builder.register_widget::<TermCanvas>(canvas::TAG);
+    builder
+        .component(
+            "main",
+            templates.get("ui.aml")?,
+            UIMain::new(farewell, &settings),
+            UIMainState::new(
+                connection,
+                screens.clone(),
+                settings.clone(),
+                name.to_string(),
+            ),
+        )
+        .unwrap();
+
+    // the statusline's closure takes the settings, the statusfeed needs
+    // a little of them too
+    let feed_colors = settings.config.colors.clone();
+    let feed_state_colors = feed_colors.clone();
+    let notice_seconds = settings.config.notice_seconds;
+
+    // statusline prototype
+    builder
+        .prototype(
+            "statusline",
+            templates.get("statusline.aml")?,
+            StatusLine::new,
+            move || {
+                StatusLineState::new(
+                    settings.prefix,
+                    settings.config.colors.clone(),
+                    settings.segments.clone(),
+                )
+            },
+        )
+        .unwrap();
+
+    // statusfeed prototype
+    builder
+        .prototype(
+            "statusfeed",
+            templates.get("statusfeed.aml")?,
+            move || StatusFeed::new(feed_colors.clone(), notice_seconds),
+            move || StatusFeedState::new(&feed_state_colors),
+        )
+        .unwrap();
+
+    // canvasfx prototype
+    builder
+        .prototype(
+            "canvasfx",
+            templates.get("canvasfx.aml")?,
+            move || CanvasFX::new(screens.clone()),
+            CanvasFXState::new,
+        )
+        .unwrap();
+    Ok(())
+}
 
-        let mut output: String = Default::default();
-        let mut lines = smol::io::BufReader::new(smol::Unblock::new(reader)).lines();
-        while let Some(line) = lines.next().await {
-            let line = line.map_err(|e| anyhow!("problem reading line: {}", e))?;
-            for c in line.escape_debug() {
-                output.push(c);
-            }
-            output.push('\n');
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use headless::Ui;
+
+    fn one_pane(id: u32, rows: u16, cols: u16) -> View {
+        let pane = PaneId::from_raw(id);
+        View {
+            panes: vec![(pane, Rect::new(0, 0, cols, rows))],
+            focused: Some(pane),
+            tabs: vec![Tab {
+                label: " 0:bash* ".to_string(),
+                theme: 1,
+            }],
+            ..View::default()
         }
-        */
+    }
+
+    fn pane_text(dump: &str) -> ServerMessage {
+        ServerMessage::Screen {
+            pane: PaneId::from_raw(0),
+            rows: 7,
+            cols: 60,
+            dump: dump.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn tells_the_server_how_much_room_panes_have() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            let received = ui.server.received();
+            assert!(matches!(
+                received[..],
+                [ClientMessage::Resize { rows: 7, cols: 60 }]
+            ));
+        });
+    }
+
+    #[test]
+    fn draws_the_tabs_and_the_focused_pane() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(0, 7, 60))));
+            ui.receive(pane_text("hello\r\nworld"));
+            ui.ticks(3);
+            assert!(ui.line(0).contains(" 0:bash* "), "{}", ui.text());
+            assert_eq!(ui.line(1), "hello");
+            assert_eq!(ui.line(2), "world");
+        });
+    }
+
+    #[test]
+    fn wide_and_combining_characters_line_up() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(0, 7, 60))));
+            // each line goes on at a set column, which is only right after
+            // the characters before it if they took the columns they should
+            ui.receive(pane_text(
+                "日本語\x1b[7G|\r\ncafe\u{301}\x1b[5G|\r\n👍🏽\x1b[3G|",
+            ));
+            ui.ticks(3);
+            assert_eq!(ui.line(1), "日本語|");
+            assert_eq!(ui.line(2), "cafe\u{301}|");
+            assert_eq!(ui.line(3), "👍🏽|");
+        });
+    }
+
+    #[test]
+    fn typing_goes_to_the_focused_pane() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(3, 7, 60))));
+            ui.server.received();
+            ui.type_text("ls\r");
+            let typed: Vec<u8> = ui
+                .server
+                .received()
+                .into_iter()
+                .flat_map(|message| match message {
+                    ClientMessage::Command(UserRequestType::SendKeys { pane, bytes }) => {
+                        assert_eq!(pane, PaneId::from_raw(3));
+                        bytes
+                    }
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect();
+            assert_eq!(typed, b"ls\r");
+        });
+    }
+
+    #[test]
+    fn mouse_events_go_to_panes_that_asked_for_them() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(0, 7, 60))));
+            ui.receive(pane_text("plain"));
+            ui.server.received();
+            ui.mouse(5, 3, MouseState::Down(MouseButton::Left));
+            assert!(ui.server.received().is_empty());
+
+            ui.receive(pane_text("\x1b[?1002h\x1b[?1006h"));
+            ui.mouse(5, 3, MouseState::Down(MouseButton::Left));
+            ui.mouse(6, 3, MouseState::Drag(MouseButton::Left));
+            ui.mouse(6, 3, MouseState::Up(MouseButton::Left));
+            // a row lower on screen, for the statusline
+            let sent: Vec<Vec<u8>> = ui
+                .server
+                .received()
+                .into_iter()
+                .map(|message| match message {
+                    ClientMessage::Command(UserRequestType::SendKeys { bytes, .. }) => bytes,
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect();
+            assert_eq!(
+                sent,
+                [&b"\x1b[<0;6;3M"[..], b"\x1b[<32;7;3M", b"\x1b[<0;7;3m"]
+            );
+        });
+    }
 
-        let doc = Document::new("@main");
+    #[test]
+    fn the_mouse_picks_tabs_and_panes_and_drags_dividers() {
+        let (left, right) = (PaneId::from_raw(0), PaneId::from_raw(1));
+        let view = View {
+            panes: vec![
+                (left, Rect::new(0, 0, 29, 7)),
+                (right, Rect::new(30, 0, 30, 7)),
+            ],
+            focused: Some(left),
+            tabs: vec![
+                Tab {
+                    label: " 0:bash* ".to_string(),
+                    theme: 1,
+                },
+                Tab {
+                    label: " 1:vim ".to_string(),
+                    theme: 0,
+                },
+            ],
+            ..View::default()
+        };
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(view)));
+            ui.receive(pane_text("plain"));
+            ui.server.received();
+            // the tabs come after " C-a "
+            ui.mouse(16, 0, MouseState::Down(MouseButton::Left));
+            ui.mouse(40, 3, MouseState::Down(MouseButton::Left));
+            ui.mouse(29, 3, MouseState::Down(MouseButton::Left));
+            ui.mouse(25, 4, MouseState::Drag(MouseButton::Left));
+            ui.mouse(25, 4, MouseState::Up(MouseButton::Left));
+            ui.mouse(5, 3, MouseState::ScrollUp);
+            let sent: Vec<UserRequestType> = ui
+                .server
+                .received()
+                .into_iter()
+                .map(|message| match message {
+                    ClientMessage::Command(command) => command,
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect();
+            assert_eq!(
+                sent,
+                [
+                    UserRequestType::SelectWindow(1),
+                    UserRequestType::Focus(right),
+                    UserRequestType::MoveDivider {
+                        pane: left,
+                        axis: Axis::Horizontal,
+                        to: 25,
+                    },
+                    UserRequestType::CopyMode {
+                        pane: left,
+                        on: true,
+                    },
+                    UserRequestType::Scroll {
+                        pane: left,
+                        lines: WHEEL_LINES,
+                    },
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn panes_are_picked_by_side_and_number() {
+        let (left, right) = (PaneId::from_raw(0), PaneId::from_raw(1));
+        let view = View {
+            panes: vec![
+                (left, Rect::new(0, 0, 29, 7)),
+                (right, Rect::new(30, 0, 30, 7)),
+            ],
+            focused: Some(left),
+            ..one_pane(0, 7, 60)
+        };
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(view)));
+            ui.receive(pane_text("plain"));
+            ui.ticks(2);
+            ui.server.received();
+            // the right pane has no screen yet, and fizzes
+            let divider = format!("plain{}│", " ".repeat(24));
+            assert!(ui.line(1).starts_with(&divider), "{}", ui.text());
+            assert_eq!(ui.backend.style_at(29, 1).fg, Some(Color::Red));
+
+            ui.press(KeyCode::Char('a'), true);
+            ui.press(KeyCode::Right, false);
+            ui.command('Q');
+            // panes are drawn at the animation's rate
+            ui.ticks(4);
+            assert!(ui.line(4).contains(" 0 "), "{}", ui.text());
+            ui.type_text("1");
+            ui.wait(NUMBERS_SHOWN);
+            assert!(!ui.line(4).contains(" 0 "), "{}", ui.text());
+            let sent: Vec<UserRequestType> = ui
+                .server
+                .received()
+                .into_iter()
+                .map(|message| match message {
+                    ClientMessage::Command(command) => command,
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect();
+            assert_eq!(
+                sent,
+                [UserRequestType::Focus(right), UserRequestType::Focus(right)]
+            );
+        });
+    }
 
-        let mut backend = {
-            let mut inst = TuiBackend::builder()
-                .enable_alt_screen()
-                .enable_raw_mode()
-                .hide_cursor()
-                .finish()
-                .unwrap();
-            inst.finalize();
-            inst
+    #[test]
+    fn a_zoomed_pane_fills_the_window_until_put_back() {
+        let (left, right) = (PaneId::from_raw(0), PaneId::from_raw(1));
+        let mut window = window::Window::default();
+        window.layout.split(None, left, Axis::Horizontal);
+        window.layout.split(Some(left), right, Axis::Horizontal);
+        window.layout.resize(left, Axis::Horizontal, 0.2);
+        window.focus(left);
+        let area = Rect::new(0, 0, 60, 7);
+        let split = window.arrange(area);
+
+        window.zoomed = true;
+        assert_eq!(window.arrange(area), [(left, area)]);
+        // going to another pane puts the split back the way it was
+        window.focus(right);
+        assert!(!window.zoomed);
+        assert_eq!(window.arrange(area), split);
+
+        let view = View {
+            zoomed: true,
+            tabs: vec![Tab {
+                label: " 0:bash*Z ".to_string(),
+                theme: 1,
+            }],
+            ..one_pane(0, 7, 60)
         };
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(view)));
+            ui.server.received();
+            assert!(ui.line(0).contains("0:bash*Z"), "{}", ui.text());
+            ui.command('z');
+            assert!(matches!(
+                ui.server.received()[..],
+                [ClientMessage::Command(UserRequestType::Zoom(pane))] if pane == left
+            ));
+        });
+    }
+
+    #[test]
+    fn the_prefix_runs_a_binding_and_times_out() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(0, 7, 60))));
+            ui.server.received();
+            ui.command('c');
+            assert!(matches!(
+                ui.server.received()[..],
+                [ClientMessage::Command(UserRequestType::NewWindow)]
+            ));
+
+            ui.press(KeyCode::Char('a'), true);
+            ui.wait(prefix::PREFIX_TIMEOUT);
+            ui.press(KeyCode::Char('c'), false);
+            assert!(matches!(
+                ui.server.received()[..],
+                [ClientMessage::Command(UserRequestType::SendKeys { .. })]
+            ));
+        });
+    }
+
+    #[test]
+    fn rename_pane_asks_for_a_title() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(3, 7, 60))));
+            ui.server.received();
+            ui.command('T');
+            ui.ticks(1);
+            assert!(ui.line(0).contains("rename pane: _"), "{}", ui.text());
+            ui.type_text("logs");
+            ui.press(KeyCode::Enter, false);
+            assert!(matches!(
+                &ui.server.received()[..],
+                [ClientMessage::Command(UserRequestType::Rename { pane, title })]
+                    if *pane == PaneId::from_raw(3) && title == "logs"
+            ));
+        });
+    }
 
-        let mut builder = Runtime::builder(doc, &backend);
-        builder
-            .component("main", "src/ui.aml", UIMain::new(), UIMainState::new())
-            .unwrap();
-
-        // statusline prototype
-        builder
-            .prototype(
-                "statusline",
-                "src/statusline.aml",
-                StatusLine::new,
-                StatusLineState::new,
-            )
-            .unwrap();
-
-        // statusfeed prototype
-        builder
-            .prototype(
-                "statusfeed",
-                "src/statusfeed.aml",
-                StatusFeed::new,
-                StatusFeedState::new,
-            )
-            .unwrap();
-
-        // canvasfx prototype
-        builder
-            .prototype(
-                "canvasfx",
-                "src/canvasfx.aml",
-                CanvasFX::new,
-                CanvasFXState::new,
-            )
-            .unwrap();
-
-        builder
-            .finish(&mut backend, |runtime, backend| runtime.run(backend))
-            .unwrap();
-
-        Ok(())
-    })
+    #[test]
+    fn notices_show_in_their_colours_until_they_expire() {
+        let config = Config::default();
+        let seconds = config.notice_seconds;
+        headless::run(8, 60, config, |ui: &mut Ui| {
+            ui.receive(ServerMessage::Notice(Notice::error("no such pane %9")));
+            let line = ui.line(0);
+            let at = line.find("no such pane %9").expect(&line);
+            let style = ui.backend.style_at(line[..at].chars().count(), 0);
+            assert_eq!(style.bg, Some(Color::Red));
+
+            ui.wait(Duration::from_secs(seconds));
+            ui.ticks(2);
+            assert!(!ui.line(0).contains("no such pane"), "{}", ui.text());
+        });
+    }
+
+    #[test]
+    fn the_message_log_opens_over_the_panes() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::View(Box::new(one_pane(0, 7, 60))));
+            ui.receive(ServerMessage::History(vec![Notice::info(
+                "reloaded the config",
+            )]));
+            ui.receive(ServerMessage::Notice(Notice::warn(
+                "bell in window 1, pane %2",
+            )));
+            ui.command('~');
+            let text = ui.text();
+            assert!(text.contains("info  reloaded the config"), "{text}");
+            assert!(text.contains("warn  bell in window 1, pane %2"), "{text}");
+
+            ui.press(KeyCode::Char('q'), false);
+            assert!(!ui.text().contains("reloaded the config"));
+            assert!(ui.server.received().iter().all(|message| !matches!(
+                message,
+                ClientMessage::Command(UserRequestType::SendKeys { .. })
+            )));
+        });
+    }
+
+    #[test]
+    fn detaching_stops_the_ui() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.command('d');
+            assert!(ui.stopped());
+            assert_eq!(ui.farewell(), "detached");
+        });
+    }
+
+    #[test]
+    fn the_session_ending_stops_the_ui() {
+        headless::run(8, 60, Config::default(), |ui: &mut Ui| {
+            ui.receive(ServerMessage::Exit);
+            assert!(ui.stopped());
+            assert_eq!(ui.farewell(), "exited");
+        });
+    }
 }
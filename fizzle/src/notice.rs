@@ -0,0 +1,63 @@
+use crate::clock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How many notices the message log keeps, the oldest go first.
+const LOG_LEN: usize = 500;
+
+/// How much a notice matters, which decides its colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+/// Something worth telling whoever is attached: shown in the statusfeed
+/// for a while, and kept in the message log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notice {
+    pub level: Level,
+    pub text: String,
+    /// When it happened, in seconds since the epoch.
+    pub at: u64,
+}
+
+impl Notice {
+    pub fn new(level: Level, text: impl Into<String>) -> Self {
+        Self {
+            level,
+            text: text.into(),
+            at: clock::current_second(),
+        }
+    }
+
+    pub fn info(text: impl Into<String>) -> Self {
+        Self::new(Level::Info, text)
+    }
+
+    pub fn warn(text: impl Into<String>) -> Self {
+        Self::new(Level::Warn, text)
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self::new(Level::Error, text)
+    }
+}
+
+/// The last notices, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Log(VecDeque<Notice>);
+
+impl Log {
+    pub fn push(&mut self, notice: Notice) {
+        if self.0.len() == LOG_LEN {
+            self.0.pop_front();
+        }
+        self.0.push_back(notice);
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Notice> {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,114 @@
+use crate::clock;
+use anathema::component::{KeyCode, KeyEvent};
+use anyhow::anyhow;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long the prefix stays armed waiting for a command key.
+pub const PREFIX_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The key that switches the next key press into a multiplexer command,
+/// written the tmux way: `C-a`, `C-b`, or a bare character like `` ` ``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixKey {
+    c: char,
+    ctrl: bool,
+}
+
+impl PrefixKey {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let (ctrl, rest) = match s.strip_prefix("C-") {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Self {
+                c: c.to_ascii_lowercase(),
+                ctrl,
+            }),
+            _ => Err(anyhow!("bad prefix key {s:?}, expected something like C-a")),
+        }
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) => key.ctrl == self.ctrl && c.to_ascii_lowercase() == self.c,
+            // crossterm reports ctrl-c on its own
+            KeyCode::CtrlC => self.ctrl && self.c == 'c',
+            _ => false,
+        }
+    }
+}
+
+impl Default for PrefixKey {
+    fn default() -> Self {
+        Self { c: 'a', ctrl: true }
+    }
+}
+
+impl fmt::Display for PrefixKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "C-")?;
+        }
+        write!(f, "{}", self.c)
+    }
+}
+
+/// What a key press turned out to be once the prefix has had its say.
+pub enum KeyRoute {
+    /// Goes to the focused pane untouched.
+    Forward,
+    /// The prefix itself, now waiting for a command key.
+    Armed,
+    /// The key following the prefix.
+    Command,
+}
+
+/// Tracks whether the prefix was pressed and is still waiting for its
+/// command key.
+#[derive(Debug, Default)]
+pub struct PrefixState {
+    key: PrefixKey,
+    armed: Option<Instant>,
+}
+
+impl PrefixState {
+    pub fn new(key: PrefixKey) -> Self {
+        Self { key, armed: None }
+    }
+
+    pub fn key(&self) -> PrefixKey {
+        self.key
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed.is_some()
+    }
+
+    /// Disarm if the command key took too long.
+    pub fn expire(&mut self) {
+        if self
+            .armed
+            .is_some_and(|since| clock::since(since) >= PREFIX_TIMEOUT)
+        {
+            self.armed = None;
+        }
+    }
+
+    /// Decide where `key` goes. Pressing the prefix twice sends it
+    /// through to the pane as a literal key.
+    pub fn route(&mut self, key: &KeyEvent) -> KeyRoute {
+        self.expire();
+        match self.armed.take() {
+            Some(_) if self.key.matches(key) => KeyRoute::Forward,
+            Some(_) => KeyRoute::Command,
+            None if self.key.matches(key) => {
+                self.armed = Some(clock::now());
+                KeyRoute::Armed
+            }
+            None => KeyRoute::Forward,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::loom::PaneId;
+use anathema::component::{KeyCode, KeyEvent};
+
+/// What the text typed into a prompt is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    RenamePane(PaneId),
+    RenameWindow(usize),
+    /// A search in the pane's copy mode, run again as the query changes.
+    Search {
+        pane: PaneId,
+        backward: bool,
+        regex: bool,
+    },
+}
+
+/// Where a key press left the prompt.
+pub enum PromptEvent {
+    Editing,
+    /// The input is different now, or how it's taken.
+    Edited(PromptKind, String),
+    Submit(PromptKind, String),
+    Cancel(PromptKind),
+}
+
+/// A one line text input shown in the statusline, it takes every key
+/// until it is submitted or cancelled.
+#[derive(Debug)]
+pub struct Prompt {
+    kind: PromptKind,
+    label: &'static str,
+    input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, label: &'static str, input: String) -> Self {
+        Self { kind, label, input }
+    }
+
+    pub fn kind(&self) -> PromptKind {
+        self.kind
+    }
+
+    pub fn key(&mut self, key: &KeyEvent) -> PromptEvent {
+        match key.code {
+            KeyCode::Enter => {
+                return PromptEvent::Submit(self.kind, std::mem::take(&mut self.input));
+            }
+            KeyCode::Esc | KeyCode::CtrlC => return PromptEvent::Cancel(self.kind),
+            KeyCode::Char('u') if key.ctrl => {
+                self.input.clear();
+            }
+            KeyCode::Char(c) if !key.ctrl => {
+                self.input.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            // searches switch between plain text and regex
+            KeyCode::Tab => match &mut self.kind {
+                PromptKind::Search { regex, .. } => *regex = !*regex,
+                _ => return PromptEvent::Editing,
+            },
+            _ => return PromptEvent::Editing,
+        }
+        PromptEvent::Edited(self.kind, self.input.clone())
+    }
+
+    /// The prompt as it should read on screen.
+    pub fn display(&self) -> String {
+        let how = match self.kind {
+            PromptKind::Search { regex: true, .. } => " (regex)",
+            _ => "",
+        };
+        format!("{}{how}: {}_", self.label, self.input)
+    }
+}
@@ -0,0 +1,226 @@
+use crate::command::UserRequestType;
+use crate::loom::PaneId;
+use crate::notice::Notice;
+use crate::session::View;
+use anyhow::anyhow;
+use futures::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::PathBuf;
+
+/// Anything bigger than this is a broken stream rather than a message.
+const MAX_MESSAGE_LEN: u32 = 64 << 20;
+
+/// What a client tells the session server.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Start sending the layout and pane screens.
+    Attach,
+    /// The room the client has for panes changed.
+    Resize {
+        rows: u16,
+        cols: u16,
+    },
+    Command(UserRequestType),
+    /// Ask for a summary of the session, for `fizzle ls`.
+    Query,
+}
+
+/// What the session server tells its clients.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ServerMessage {
+    View(Box<View>),
+    /// Everything needed to draw a pane, as escape sequences for a fresh
+    /// `rows` x `cols` terminal.
+    Screen {
+        pane: PaneId,
+        rows: u16,
+        cols: u16,
+        dump: Vec<u8>,
+    },
+    /// How a command sent by this client went, or something that happened
+    /// in the session.
+    Notice(Notice),
+    /// What the session told about before this client attached.
+    History(Vec<Notice>),
+    /// Text this client yanked, for the clipboard of the terminal it runs in.
+    Clipboard(String),
+    Info(SessionInfo),
+    /// The session is over, the client should go too.
+    Exit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub windows: usize,
+    pub panes: usize,
+    /// How many clients are attached.
+    pub attached: usize,
+}
+
+/// A message as it goes over the socket: its length as a little endian
+/// `u32`, then the message itself.
+pub fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    let body = bincode::serialize(message).expect("protocol messages always serialize");
+    let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+    frame.extend_from_slice(&body);
+    frame
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> io::Result<T> {
+    bincode::deserialize(body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn body_len(header: [u8; 4]) -> io::Result<usize> {
+    match u32::from_le_bytes(header) {
+        len if len > MAX_MESSAGE_LEN => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {len} bytes is too big"),
+        )),
+        len => Ok(len as usize),
+    }
+}
+
+/// Read the next message, `None` once the other side hung up.
+pub fn read<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<Option<T>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let mut body = vec![0u8; body_len(header)?];
+    reader.read_exact(&mut body)?;
+    decode(&body).map(Some)
+}
+
+/// [`read`] for the server's async streams.
+pub async fn read_async<T: DeserializeOwned>(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> io::Result<Option<T>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header).await {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let mut body = vec![0u8; body_len(header)?];
+    reader.read_exact(&mut body).await?;
+    decode(&body).map(Some)
+}
+
+/// Where session sockets live, created on first use and only readable by
+/// the current user.
+pub fn socket_dir() -> anyhow::Result<PathBuf> {
+    // SAFETY: getuid can't fail and touches nothing
+    let uid = unsafe { libc::getuid() };
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) => PathBuf::from(runtime).join("fizzle"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_else(|_| uid.to_string());
+            std::env::temp_dir().join(format!("fizzle-{user}"))
+        }
+    };
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)?;
+    // creating it does nothing if it was there already, and anyone can
+    // make a directory in /tmp for someone else's sockets to go into
+    let meta = std::fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o777 != 0o700 {
+        return Err(anyhow!(
+            "{} isn't a private directory of this user's, not putting sockets there",
+            dir.display()
+        ));
+    }
+    Ok(dir)
+}
+
+pub fn socket_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\0']) || name.starts_with('.') {
+        return Err(anyhow!("bad session name {name:?}"));
+    }
+    Ok(socket_dir()?.join(name))
+}
+
+/// Names of the sessions with a socket, most recently started first.
+/// Sockets left behind by servers that died are cleaned up on the way.
+pub fn sessions() -> anyhow::Result<Vec<String>> {
+    let mut found = vec![];
+    for entry in std::fs::read_dir(socket_dir()?)? {
+        let entry = entry?;
+        let path = entry.path();
+        if std::os::unix::net::UnixStream::connect(&path).is_err() {
+            _ = std::fs::remove_file(&path);
+            continue;
+        }
+        let started = entry.metadata()?.modified()?;
+        found.push((started, entry.file_name().to_string_lossy().into_owned()));
+    }
+    found.sort_by_key(|(started, _)| std::cmp::Reverse(*started));
+    Ok(found.into_iter().map(|(_, name)| name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_messages() -> Vec<u8> {
+        let mut bytes = encode(&ClientMessage::Resize { rows: 24, cols: 80 });
+        bytes.extend(encode(&ClientMessage::Command(UserRequestType::Quit)));
+        bytes
+    }
+
+    #[test]
+    fn messages_come_back_out_as_they_went_in() {
+        let bytes = two_messages();
+        let mut reader = &bytes[..];
+        assert!(matches!(
+            read(&mut reader).unwrap(),
+            Some(ClientMessage::Resize { rows: 24, cols: 80 })
+        ));
+        assert!(matches!(
+            read(&mut reader).unwrap(),
+            Some(ClientMessage::Command(UserRequestType::Quit))
+        ));
+        assert!(read::<ClientMessage>(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn the_async_reader_reads_the_same() {
+        let bytes = two_messages();
+        let mut reader = &bytes[..];
+        smol::block_on(async {
+            assert!(matches!(
+                read_async(&mut reader).await.unwrap(),
+                Some(ClientMessage::Resize { rows: 24, cols: 80 })
+            ));
+            assert!(matches!(
+                read_async(&mut reader).await.unwrap(),
+                Some(ClientMessage::Command(UserRequestType::Quit))
+            ));
+            assert!(
+                read_async::<ClientMessage>(&mut reader)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        });
+    }
+
+    #[test]
+    fn broken_streams_are_errors() {
+        // cut off in the middle of a message
+        let bytes = two_messages();
+        let mut reader = &bytes[..bytes.len() - 1];
+        read::<ClientMessage>(&mut reader).unwrap();
+        assert!(read::<ClientMessage>(&mut reader).is_err());
+
+        let too_big = (MAX_MESSAGE_LEN + 1).to_le_bytes();
+        assert!(read::<ClientMessage>(&mut &too_big[..]).is_err());
+        let garbage = [1, 0, 0, 0, 0xff];
+        let err = smol::block_on(read_async::<ClientMessage>(&mut &garbage[..])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
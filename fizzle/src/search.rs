@@ -0,0 +1,266 @@
+use crate::copy::Point;
+use alacrema_term::Screen;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// What to look for, as typed into the search prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub text: String,
+    pub backward: bool,
+    /// A regular expression rather than the text as is.
+    pub regex: bool,
+}
+
+/// How a pane's search went, for the statusfeed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchStatus {
+    pub query: Query,
+    /// Which match the cursor is on, counting from the top of the
+    /// scrollback.
+    pub current: Option<usize>,
+    pub total: usize,
+    /// The query doesn't compile as a regular expression.
+    pub invalid: bool,
+}
+
+impl SearchStatus {
+    /// A short summary like `3/17`, or why there's nothing.
+    pub fn summary(&self) -> String {
+        match (self.invalid, self.current, self.total) {
+            (true, _, _) => "bad regex".to_string(),
+            (_, _, 0) if self.query.text.is_empty() => String::new(),
+            (_, _, 0) => "no matches".to_string(),
+            (_, Some(i), total) => format!("{}/{total}", i + 1),
+            (_, None, total) => format!("{total} matches"),
+        }
+    }
+}
+
+/// A search through a pane's scrollback and screen. Lines are searched
+/// one at a time, a match can't span two of them.
+#[derive(Debug)]
+pub struct Search {
+    query: Query,
+    pattern: Option<Regex>,
+    // the whole buffer's matches as of the last search, for the count
+    status: SearchStatus,
+}
+
+impl Search {
+    pub fn new(query: Query) -> Self {
+        let pattern = compile(&query);
+        let invalid = !query.text.is_empty() && pattern.is_none();
+        Self {
+            status: SearchStatus {
+                query: query.clone(),
+                current: None,
+                total: 0,
+                invalid,
+            },
+            query,
+            pattern,
+        }
+    }
+
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    pub fn status(&self) -> &SearchStatus {
+        &self.status
+    }
+
+    /// Where the matches on buffer line `line` start and end, end
+    /// exclusive.
+    pub fn matches_on(&self, screen: &Screen, line: usize) -> Vec<(usize, usize)> {
+        let Some(pattern) = &self.pattern else {
+            return vec![];
+        };
+        let (text, cols) = line_text(screen, line);
+        pattern
+            .find_iter(&text)
+            .filter(|m| !m.is_empty())
+            .map(|m| (cols[m.start()], cols[m.end()]))
+            .collect()
+    }
+
+    /// The nearest match after `from`, or before it going backward,
+    /// wrapping around the ends of the buffer.
+    pub fn find(&self, screen: &Screen, from: Point, backward: bool) -> Option<Point> {
+        self.pattern.as_ref()?;
+        let len = screen.buffer_len();
+        // from's own line comes up twice, for what's on either side of it
+        for step in 0..=len {
+            let line = if backward {
+                (from.line + len * 2 - step) % len
+            } else {
+                (from.line + step) % len
+            };
+            let starts = self
+                .matches_on(screen, line)
+                .into_iter()
+                .map(|(col, _)| col);
+            let found = if backward {
+                starts
+                    .filter(|col| step != 0 || *col < from.col)
+                    .rfind(|col| step != len || *col >= from.col)
+            } else {
+                starts
+                    .filter(|col| step != 0 || *col > from.col)
+                    .find(|col| step != len || *col <= from.col)
+            };
+            if let Some(col) = found {
+                return Some(Point { line, col });
+            }
+        }
+        None
+    }
+
+    /// Count the matches again, with the cursor at `cursor`.
+    pub fn update_status(&mut self, screen: &Screen, cursor: Point) {
+        let mut total = 0;
+        let mut current = None;
+        for line in 0..screen.buffer_len() {
+            for (col, _) in self.matches_on(screen, line) {
+                if (Point { line, col }) == cursor {
+                    current = Some(total);
+                }
+                total += 1;
+            }
+        }
+        self.status.current = current;
+        self.status.total = total;
+    }
+}
+
+/// Searches without capitals in them ignore case, like vim's smartcase.
+fn compile(query: &Query) -> Option<Regex> {
+    if query.text.is_empty() {
+        return None;
+    }
+    let pattern = if query.regex {
+        query.text.clone()
+    } else {
+        regex::escape(&query.text)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.text.chars().any(char::is_uppercase))
+        .build()
+        .ok()
+}
+
+/// A buffer line as text, and the column each byte of it is in. One past
+/// the end is the end of the line.
+fn line_text(screen: &Screen, line: usize) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut cols = vec![];
+    if let Some(row) = screen.buffer_line(line) {
+        for col in 0..screen.cols() {
+            match row.cells.get(col) {
+                Some(cell) => text.extend(cell.chars()),
+                None => text.push(' '),
+            }
+            cols.resize(text.len(), col);
+        }
+    }
+    cols.push(screen.cols());
+    (text, cols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacrema_term::Terminal;
+
+    fn terminal(text: &str) -> Terminal {
+        let mut terminal = Terminal::new(3, 20);
+        terminal.advance(text.as_bytes());
+        terminal
+    }
+
+    fn query(text: &str, regex: bool) -> Query {
+        Query {
+            text: text.to_string(),
+            backward: false,
+            regex,
+        }
+    }
+
+    fn at(line: usize, col: usize) -> Point {
+        Point { line, col }
+    }
+
+    #[test]
+    fn finds_the_next_match_either_way_and_wraps_around() {
+        let terminal = terminal("foo bar\r\nbar\r\nfoo");
+        let screen = terminal.screen();
+        let search = Search::new(query("bar", false));
+        assert_eq!(search.find(screen, at(0, 0), false), Some(at(0, 4)));
+        assert_eq!(search.find(screen, at(0, 4), false), Some(at(1, 0)));
+        assert_eq!(search.find(screen, at(1, 0), false), Some(at(0, 4)));
+        assert_eq!(search.find(screen, at(1, 0), true), Some(at(0, 4)));
+        assert_eq!(search.find(screen, at(0, 4), true), Some(at(1, 0)));
+    }
+
+    #[test]
+    fn the_only_match_is_found_from_anywhere_on_its_line() {
+        let terminal = terminal("a needle in a line");
+        let screen = terminal.screen();
+        let search = Search::new(query("needle", false));
+        // past it going forward, the whole buffer is gone through and it
+        // comes up again at the end
+        assert_eq!(search.find(screen, at(0, 10), false), Some(at(0, 2)));
+        assert_eq!(search.find(screen, at(0, 0), true), Some(at(0, 2)));
+        // on it, it's the next one after itself
+        assert_eq!(search.find(screen, at(0, 2), false), Some(at(0, 2)));
+    }
+
+    #[test]
+    fn capitals_make_it_match_case() {
+        let terminal = terminal("Error\r\nerror");
+        let screen = terminal.screen();
+        let lower = Search::new(query("error", false));
+        assert_eq!(lower.matches_on(screen, 0), [(0, 5)]);
+        assert_eq!(lower.matches_on(screen, 1), [(0, 5)]);
+        let upper = Search::new(query("Error", false));
+        assert_eq!(upper.matches_on(screen, 1), []);
+    }
+
+    #[test]
+    fn regexes_match_as_patterns_and_text_as_is() {
+        let terminal = terminal("a.b axb");
+        let screen = terminal.screen();
+        assert_eq!(
+            Search::new(query("a.b", true)).matches_on(screen, 0),
+            [(0, 3), (4, 7)]
+        );
+        assert_eq!(
+            Search::new(query("a.b", false)).matches_on(screen, 0),
+            [(0, 3)]
+        );
+        let bad = Search::new(query("a(", true));
+        assert!(bad.status().invalid);
+        assert_eq!(bad.find(screen, at(0, 0), false), None);
+    }
+
+    #[test]
+    fn the_status_counts_matches_around_the_cursor() {
+        let terminal = terminal("ab ab\r\nab");
+        let screen = terminal.screen();
+        let mut search = Search::new(query("ab", false));
+        search.update_status(screen, at(0, 3));
+        assert_eq!(search.status().summary(), "2/3");
+        search.update_status(screen, at(2, 0));
+        assert_eq!(search.status().summary(), "3 matches");
+
+        let mut missing = Search::new(query("zz", false));
+        missing.update_status(screen, at(0, 0));
+        assert_eq!(missing.status().summary(), "no matches");
+        assert_eq!(Search::new(query("", false)).status().summary(), "");
+        assert_eq!(
+            Search::new(query("(", true)).status().summary(),
+            "bad regex"
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use crate::command::CommandOutcome;
+use crate::config;
+use crate::loom::{PaneId, PseudoTerminalLoom};
+use crate::notice::Notice;
+use crate::protocol::{self, ClientMessage, ServerMessage};
+use crate::session::{Session, View};
+use anyhow::anyhow;
+use futures::prelude::*;
+use smol::channel::{Receiver, Sender, TrySendError};
+use smol::{Async, Timer};
+use std::collections::HashMap;
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often attached clients are sent whatever changed.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// How many messages can wait for a client before it's taken to have
+/// stopped reading.
+const QUEUE: usize = 64;
+
+/// How long a freshly started server gets to open its socket.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+type ClientId = u64;
+
+enum Event {
+    Connected(ClientId, Box<Client>),
+    Message(ClientId, ClientMessage),
+    Gone(ClientId),
+    Frame,
+}
+
+struct Client {
+    stream: Arc<Async<UnixStream>>,
+    out: Sender<ServerMessage>,
+    // the writer's end too, to take back what it hasn't written yet
+    queued: Receiver<ServerMessage>,
+    attached: bool,
+    /// The room it has for panes, once it said.
+    size: Option<(u16, u16)>,
+    // what it was last sent, to only send changes
+    view: Option<View>,
+    generations: HashMap<PaneId, u64>,
+    writer: smol::Task<()>,
+}
+
+impl Client {
+    fn new(stream: Arc<Async<UnixStream>>) -> Self {
+        let (out, queued) = smol::channel::bounded(QUEUE);
+        Self {
+            writer: smol::spawn(write_client(stream.clone(), queued.clone())),
+            stream,
+            out,
+            queued,
+            attached: false,
+            size: None,
+            view: None,
+            generations: HashMap::new(),
+        }
+    }
+
+    fn send(&mut self, message: ServerMessage) {
+        // a client that went away is cleaned up once its reader notices
+        let Err(TrySendError::Full(message)) = self.out.try_send(message) else {
+            return;
+        };
+        // it stopped reading. The screens waiting for it are out of date
+        // by now, so they go and it's sent everything again once there's
+        // room, rather than every frame piling up in the meantime
+        let keep: Vec<_> = std::iter::from_fn(|| self.queued.try_recv().ok())
+            .chain([message])
+            .filter(|message| {
+                !matches!(
+                    message,
+                    ServerMessage::View(_) | ServerMessage::Screen { .. }
+                )
+            })
+            .collect();
+        self.view = None;
+        self.generations.clear();
+        for message in keep {
+            if self.out.try_send(message).is_err() {
+                // all notices and no room, there's no catching up
+                _ = self.stream.get_ref().shutdown(Shutdown::Both);
+                return;
+            }
+        }
+    }
+}
+
+/// Start the server for `name` in the background, detached from this
+/// terminal, and wait for it to come up.
+pub fn spawn(name: &str) -> anyhow::Result<()> {
+    let path = protocol::socket_path(name)?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(anyhow!("session {name} already exists"));
+    }
+    std::process::Command::new(std::env::current_exe()?)
+        .arg("server")
+        .arg(name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // out of the terminal's foreground group, so closing it doesn't
+        // take the session down with it
+        .process_group(0)
+        .spawn()?;
+
+    let started = std::time::Instant::now();
+    while started.elapsed() < STARTUP_TIMEOUT {
+        if UnixStream::connect(&path).is_ok() {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Err(anyhow!("session {name} didn't start"))
+}
+
+/// Run the session `name` until it is killed, in the foreground.
+pub fn run(name: &str) -> anyhow::Result<()> {
+    let path = protocol::socket_path(name)?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(anyhow!("session {name} already exists"));
+    }
+    let settings = config::load()?;
+
+    // whatever is left there belongs to a server that died
+    _ = std::fs::remove_file(&path);
+    let listener = Async::new(UnixListener::bind(&path)?)?;
+    let _socket = SocketFile(path);
+
+    let mut session = Session::new(PseudoTerminalLoom::new());
+    session.configure(&settings.config);
+    let size = settings.config.default_size;
+    session.set_area(size.rows, size.cols);
+    session.startup(&settings.startup);
+
+    smol::block_on(serve(listener, session))
+}
+
+/// Removes the socket once the server is done with it.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.0);
+    }
+}
+
+async fn serve(listener: Async<UnixListener>, mut session: Session) -> anyhow::Result<()> {
+    let (events, incoming) = smol::channel::unbounded();
+    let _accept = smol::spawn(accept(listener, events.clone()));
+    let _frames = {
+        let events = events.clone();
+        smol::spawn(async move {
+            loop {
+                Timer::after(FRAME).await;
+                if events.send(Event::Frame).await.is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
+    let mut clients: HashMap<ClientId, Client> = HashMap::new();
+    while let Ok(event) = incoming.recv().await {
+        match event {
+            Event::Connected(id, client) => {
+                clients.insert(id, *client);
+            }
+            Event::Gone(id) => {
+                clients.remove(&id);
+                fit_to_clients(&mut session, &clients);
+            }
+            Event::Message(id, message) => {
+                let attached = clients.values().filter(|c| c.attached).count();
+                let Some(client) = clients.get_mut(&id) else {
+                    continue;
+                };
+                match message {
+                    ClientMessage::Attach => {
+                        client.attached = true;
+                        client.send(ServerMessage::History(session.history()));
+                        fit_to_clients(&mut session, &clients);
+                    }
+                    ClientMessage::Resize { rows, cols } => {
+                        client.size = Some((rows, cols));
+                        fit_to_clients(&mut session, &clients);
+                    }
+                    ClientMessage::Query => {
+                        client.send(ServerMessage::Info(session.info(attached)))
+                    }
+                    ClientMessage::Command(cmd) => {
+                        let notice = match session.dispatch(cmd) {
+                            Ok(CommandOutcome::Quit) => break,
+                            Ok(CommandOutcome::Copied(pane, text)) => {
+                                let report = CommandOutcome::Copied(pane, text.clone()).to_string();
+                                client.send(ServerMessage::Clipboard(text));
+                                Some(Notice::info(report))
+                            }
+                            Ok(outcome) if outcome.is_routine() => None,
+                            Ok(outcome) => Some(Notice::info(outcome.to_string())),
+                            Err(err) => Some(Notice::error(err.to_string())),
+                        };
+                        if let Some(notice) = notice {
+                            client.send(ServerMessage::Notice(notice));
+                        }
+                    }
+                }
+            }
+            Event::Frame => {
+                session.fit_panes();
+                session.reap_panes();
+                if session.is_over() {
+                    break;
+                }
+                session.collect_alerts();
+                session.collect_info();
+                let view = session.view();
+                let notices = session.take_notices();
+                for client in clients.values_mut().filter(|c| c.attached) {
+                    for notice in &notices {
+                        client.send(ServerMessage::Notice(notice.clone()));
+                    }
+                    send_changes(&session, &view, client);
+                }
+            }
+        }
+    }
+
+    // the panes go down with the session, then every client is told and
+    // given the chance to hear it before its connection closes
+    drop(session);
+    for (_, mut client) in clients.drain() {
+        client.send(ServerMessage::Exit);
+        client.out.close();
+        client.writer.await;
+    }
+    Ok(())
+}
+
+/// Size the session to the smallest attached client, so the panes fit
+/// on all of them and don't reflow every time a different one resizes.
+fn fit_to_clients(session: &mut Session, clients: &HashMap<ClientId, Client>) {
+    if let Some((rows, cols)) = smallest(clients.values()) {
+        session.set_area(rows, cols);
+    }
+}
+
+fn smallest<'a>(clients: impl Iterator<Item = &'a Client>) -> Option<(u16, u16)> {
+    clients
+        .filter(|client| client.attached)
+        .filter_map(|client| client.size)
+        .reduce(|(rows, cols), (r, c)| (rows.min(r), cols.min(c)))
+}
+
+/// Bring a client up to date with the layout and the screens it shows.
+fn send_changes(session: &Session, view: &View, client: &mut Client) {
+    if client.view.as_ref() != Some(view) {
+        client.view = Some(view.clone());
+        client.send(ServerMessage::View(Box::new(view.clone())));
+        // screens that aren't shown anymore get sent in full when they are
+        client
+            .generations
+            .retain(|id, _| view.panes.iter().any(|(pane, _)| pane == id));
+    }
+
+    for (id, _) in &view.panes {
+        let Some(pane) = session.loom().pane(*id) else {
+            continue;
+        };
+        let generation = pane.generation();
+        if client.generations.get(id) == Some(&generation) {
+            continue;
+        }
+        client.generations.insert(*id, generation);
+        let (rows, cols, dump) = pane.snapshot();
+        client.send(ServerMessage::Screen {
+            pane: *id,
+            rows,
+            cols,
+            dump,
+        });
+    }
+}
+
+async fn accept(listener: Async<UnixListener>, events: Sender<Event>) {
+    let mut next_id: ClientId = 0;
+    while let Ok((stream, _)) = listener.accept().await {
+        let id = next_id;
+        next_id += 1;
+        let stream = Arc::new(stream);
+        let client = Box::new(Client::new(stream.clone()));
+        if events.send(Event::Connected(id, client)).await.is_err() {
+            break;
+        }
+        smol::spawn(read_client(id, stream, events.clone())).detach();
+    }
+}
+
+async fn read_client(id: ClientId, stream: Arc<Async<UnixStream>>, events: Sender<Event>) {
+    let mut reader = &*stream;
+    while let Ok(Some(message)) = protocol::read_async(&mut reader).await {
+        if events.send(Event::Message(id, message)).await.is_err() {
+            return;
+        }
+    }
+    _ = events.send(Event::Gone(id)).await;
+}
+
+async fn write_client(stream: Arc<Async<UnixStream>>, outgoing: Receiver<ServerMessage>) {
+    let mut writer = &*stream;
+    while let Ok(message) = outgoing.recv().await {
+        if writer.write_all(&protocol::encode(&message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    // with nothing writing its queue out
+    fn client(size: Option<(u16, u16)>) -> (Client, UnixStream) {
+        let (stream, other) = UnixStream::pair().unwrap();
+        let (out, queued) = smol::channel::bounded(QUEUE);
+        let client = Client {
+            stream: Arc::new(Async::new(stream).unwrap()),
+            out,
+            queued,
+            attached: true,
+            size,
+            view: Some(View::default()),
+            generations: HashMap::from([(PaneId::from_raw(0), 1)]),
+            writer: smol::spawn(async {}),
+        };
+        (client, other)
+    }
+
+    fn screen() -> ServerMessage {
+        ServerMessage::Screen {
+            pane: PaneId::from_raw(0),
+            rows: 1,
+            cols: 1,
+            dump: vec![],
+        }
+    }
+
+    #[test]
+    fn a_client_that_stops_reading_drops_its_screens_and_gets_them_again() {
+        let (mut client, _other) = client(None);
+        client.send(ServerMessage::Notice(Notice::info("hi")));
+        // the last one has room again
+        for _ in 0..QUEUE + 1 {
+            client.send(screen());
+        }
+        assert_eq!(client.queued.len(), 2);
+        assert!(matches!(
+            client.queued.try_recv(),
+            Ok(ServerMessage::Notice(_))
+        ));
+        assert!(matches!(
+            client.queued.try_recv(),
+            Ok(ServerMessage::Screen { .. })
+        ));
+        assert_eq!(client.view, None);
+        assert!(client.generations.is_empty());
+    }
+
+    #[test]
+    fn a_client_that_cant_even_take_its_notices_is_hung_up_on() {
+        let (mut client, mut other) = client(None);
+        for _ in 0..=QUEUE {
+            client.send(ServerMessage::Notice(Notice::info("hi")));
+        }
+        assert_eq!(other.read(&mut [0; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn the_session_fits_the_smallest_attached_client() {
+        let (big, _a) = client(Some((50, 200)));
+        let (small, _b) = client(Some((30, 100)));
+        let (mut detached, _c) = client(Some((10, 10)));
+        detached.attached = false;
+        let (quiet, _d) = client(None);
+        let clients = [big, small, detached, quiet];
+        assert_eq!(smallest(clients.iter()), Some((30, 100)));
+        assert_eq!(smallest(clients[..1].iter()), Some((50, 200)));
+        assert_eq!(smallest(clients[2..].iter()), None);
+    }
+}
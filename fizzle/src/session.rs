@@ -0,0 +1,670 @@
+use crate::binding::Command;
+use crate::command::{CommandOutcome, UserRequestType};
+use crate::config::{self, Config};
+use crate::copy::CopyOutcome;
+use crate::layout::{Axis, Rect};
+use crate::loom::{ExitPolicy, PaneExit, PaneId, PaneInfo, PseudoTerminalLoom};
+use crate::notice::{Log, Notice};
+use crate::protocol::SessionInfo;
+use crate::search::SearchStatus;
+use crate::window::Windows;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How often what runs in each pane is looked up again.
+const INFO_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a pane set to respawn has to have run for it to be started
+/// again, so a command that fails right away isn't run over and over.
+const MIN_RESPAWN_RUNTIME: Duration = Duration::from_secs(1);
+
+/// One window in the statusline's tab bar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tab {
+    pub label: String,
+    // 0 for a plain tab, 1 for the active window, 2 for one with news
+    pub theme: u8,
+}
+
+/// What a client needs to draw the session: the active window's layout
+/// and the tab bar.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    /// The active window's panes and where they go.
+    pub panes: Vec<(PaneId, Rect)>,
+    pub focused: Option<PaneId>,
+    /// The focused pane fills the window for now.
+    pub zoomed: bool,
+    pub active: usize,
+    /// The active window's name, if it was given one.
+    pub name: Option<String>,
+    pub tabs: Vec<Tab>,
+    /// `(offset, lines)` of the focused pane's scrollback, while it's in
+    /// copy mode.
+    pub scroll: Option<(usize, usize)>,
+    /// The focused pane's search in copy mode.
+    pub search: Option<SearchStatus>,
+    /// What runs in the focused pane.
+    pub info: Option<PaneInfo>,
+    /// The active window's panes whose process is gone, and how it went.
+    pub exits: Vec<(PaneId, PaneExit)>,
+}
+
+/// The panes and windows a server keeps running while clients come and go.
+pub struct Session {
+    loom: PseudoTerminalLoom,
+    windows: Windows,
+    // the space the layout gets to divide between panes
+    area: Rect,
+    // what copy mode yanked last
+    paste_buffer: Option<String>,
+    // what runs in each pane as of the last look, and when that was
+    info: HashMap<PaneId, PaneInfo>,
+    info_checked: Option<Instant>,
+    // everything told so far, and what clients haven't heard yet
+    notices: Log,
+    fresh: Vec<Notice>,
+    // the last pane was closed, which ends the session
+    over: bool,
+}
+
+impl Session {
+    pub fn new(loom: PseudoTerminalLoom) -> Self {
+        Self {
+            loom,
+            windows: Windows::default(),
+            area: Rect::new(0, 0, 80, 24),
+            paste_buffer: None,
+            info: HashMap::new(),
+            info_checked: None,
+            notices: Log::default(),
+            fresh: vec![],
+            over: false,
+        }
+    }
+
+    pub fn loom(&self) -> &PseudoTerminalLoom {
+        &self.loom
+    }
+
+    /// Take up the config's settings for panes spawned from now on.
+    pub fn configure(&mut self, config: &Config) {
+        let argv = match &config.command {
+            Some(argv) => argv.iter().map(Into::into).collect(),
+            None => vec![std::env::var_os("SHELL").unwrap_or_else(|| "/bin/sh".into())],
+        };
+        self.loom.set_command(argv);
+        let env = config.env.iter().map(|(k, v)| (k.clone(), v.clone()));
+        self.loom.set_env(env.collect());
+        self.loom.set_history_limit(config.history);
+        self.loom.set_exit_policy(config.on_exit);
+    }
+
+    /// Run the commands a new session starts with. One that fails doesn't
+    /// stop the rest, nobody is attached yet to hear about it.
+    pub fn startup(&mut self, commands: &[Command]) {
+        for command in commands {
+            let active = self.windows.active_index();
+            if let Some(request) = command.request(self.focused(), active) {
+                _ = self.dispatch(request);
+            }
+        }
+    }
+
+    /// Lay the panes out over `rows` x `cols` from now on.
+    pub fn set_area(&mut self, rows: u16, cols: u16) {
+        self.area = Rect::new(0, 0, cols, rows);
+    }
+
+    /// Route a single command to the loom and/or the windows.
+    pub fn dispatch(&mut self, cmd: UserRequestType) -> anyhow::Result<CommandOutcome> {
+        match cmd {
+            UserRequestType::NewPane => {
+                // cells are about twice as tall as they are wide
+                let axis = match self.focused_rect() {
+                    Some(rect) if rect.height * 2 > rect.width => Axis::Vertical,
+                    _ => Axis::Horizontal,
+                };
+                self.open_pane(axis)
+            }
+            UserRequestType::Split(axis) => self.open_pane(axis),
+            UserRequestType::ClosePane(id) => {
+                self.loom.close_pane(id)?;
+                self.over = self.loom.panes().is_empty();
+                let Some(index) = self.windows.find(id) else {
+                    return Ok(CommandOutcome::Closed(id));
+                };
+                let window = self.windows.get_mut(index).unwrap();
+                window.remove(id);
+                // a window goes away with its last pane, unless it's the only one
+                if window.layout.is_empty() && self.windows.len() > 1 {
+                    self.windows.remove(index);
+                }
+                Ok(CommandOutcome::Closed(id))
+            }
+            UserRequestType::ResizeSplit { pane, axis, delta } => {
+                let window = self.windows.active_mut();
+                if !window.layout.resize(pane, axis, delta) {
+                    anyhow::bail!("pane {pane} has no split to resize that way");
+                }
+                // there's no seeing the split change from behind a zoom
+                window.zoomed = false;
+                Ok(CommandOutcome::Resized(pane))
+            }
+            UserRequestType::MoveDivider { pane, axis, to } => {
+                let layout = &mut self.windows.active_mut().layout;
+                if !layout.move_divider(pane, axis, to, self.area) {
+                    anyhow::bail!("pane {pane} has no divider to move that way");
+                }
+                Ok(CommandOutcome::Resized(pane))
+            }
+            UserRequestType::Focus(id) => {
+                let Some(index) = self.windows.find(id) else {
+                    anyhow::bail!("no such pane {id}");
+                };
+                self.windows.select(index);
+                self.windows.active_mut().focus(id);
+                Ok(CommandOutcome::Focused(id))
+            }
+            UserRequestType::Zoom(id) => {
+                let Some(index) = self.windows.find(id) else {
+                    anyhow::bail!("no such pane {id}");
+                };
+                let window = self.windows.get_mut(index).unwrap();
+                if window.focused == Some(id) && window.zoomed {
+                    window.zoomed = false;
+                    return Ok(CommandOutcome::Zoomed(id, false));
+                }
+                if window.layout.panes().len() < 2 {
+                    anyhow::bail!("nothing to zoom, pane {id} is alone in its window");
+                }
+                window.focus(id);
+                window.zoomed = true;
+                Ok(CommandOutcome::Zoomed(id, true))
+            }
+            UserRequestType::LastPane => {
+                let window = self.windows.active_mut();
+                let Some(last) = window.last else {
+                    anyhow::bail!("no last pane");
+                };
+                window.focus(last);
+                Ok(CommandOutcome::Focused(last))
+            }
+            UserRequestType::SendKeys { pane, bytes } => {
+                self.loom.write(pane, &bytes)?;
+                // typing into a pane means being back to watching it
+                self.loom.set_copy_mode(pane, false)?;
+                Ok(CommandOutcome::Sent(pane))
+            }
+            UserRequestType::Rename { pane, title } => {
+                self.loom.rename(pane, title)?;
+                Ok(CommandOutcome::Renamed(pane))
+            }
+            UserRequestType::Respawn { pane, kill } => {
+                self.loom.respawn(pane, kill)?;
+                Ok(CommandOutcome::Respawned(pane))
+            }
+            UserRequestType::SetExitPolicy { pane, policy } => {
+                self.loom.set_pane_exit_policy(pane, policy)?;
+                Ok(CommandOutcome::ExitPolicySet(pane, policy))
+            }
+            UserRequestType::CopyMode { pane, on } => {
+                self.loom.set_copy_mode(pane, on)?;
+                Ok(CommandOutcome::Scrolled(pane))
+            }
+            UserRequestType::Scroll { pane, lines } => {
+                self.loom.scroll(pane, lines)?;
+                Ok(CommandOutcome::Scrolled(pane))
+            }
+            UserRequestType::Copy { pane, action } => match self.loom.copy(pane, action)? {
+                CopyOutcome::Yanked(text) => {
+                    self.paste_buffer = Some(text.clone());
+                    Ok(CommandOutcome::Copied(pane, text))
+                }
+                CopyOutcome::Stay | CopyOutcome::Exit => Ok(CommandOutcome::Scrolled(pane)),
+            },
+            UserRequestType::Paste(pane) => {
+                let Some(text) = &self.paste_buffer else {
+                    anyhow::bail!("nothing to paste, copy something first");
+                };
+                let Some(target) = self.loom.pane(pane) else {
+                    anyhow::bail!("no such pane {pane}");
+                };
+                // newlines go in the way Enter would, and an app that asked
+                // gets told it's a paste so it won't run the lines as typed
+                let text = text.replace('\n', "\r");
+                let bytes = if target.bracketed_paste() {
+                    format!("\x1b[200~{text}\x1b[201~").into_bytes()
+                } else {
+                    text.into_bytes()
+                };
+                self.loom.set_copy_mode(pane, false)?;
+                self.loom.write(pane, &bytes)?;
+                Ok(CommandOutcome::Pasted(pane))
+            }
+            UserRequestType::NewWindow => {
+                let index = self.windows.open();
+                if let Err(err) = self.open_pane(Axis::Horizontal) {
+                    self.windows.remove(index);
+                    return Err(err);
+                }
+                Ok(CommandOutcome::WindowOpened(index))
+            }
+            UserRequestType::SelectWindow(index) => {
+                if !self.windows.select(index) {
+                    anyhow::bail!("no window {index}");
+                }
+                Ok(CommandOutcome::WindowSelected(index))
+            }
+            UserRequestType::CycleWindow(offset) => {
+                let index = self.windows.cycle(offset);
+                Ok(CommandOutcome::WindowSelected(index))
+            }
+            UserRequestType::MoveWindow(offset) => {
+                let index = self.windows.shift(offset);
+                Ok(CommandOutcome::WindowMoved(index))
+            }
+            UserRequestType::RenameWindow { window, name } => {
+                let Some(target) = self.windows.get_mut(window) else {
+                    anyhow::bail!("no window {window}");
+                };
+                // renaming to nothing goes back to following the pane title
+                target.name = Some(name).filter(|name| !name.is_empty());
+                Ok(CommandOutcome::WindowRenamed(window))
+            }
+            UserRequestType::CloseWindow(index) => {
+                let Some(window) = self.windows.remove(index) else {
+                    anyhow::bail!("no window {index}");
+                };
+                for id in window.layout.panes() {
+                    self.loom.close_pane(id)?;
+                    self.over = self.loom.panes().is_empty();
+                }
+                Ok(CommandOutcome::WindowClosed(index))
+            }
+            UserRequestType::ReloadConfig => {
+                self.configure(&config::load()?.config);
+                self.notify(Notice::info("reloaded the config"));
+                Ok(CommandOutcome::Reloaded)
+            }
+            UserRequestType::Quit => Ok(CommandOutcome::Quit),
+        }
+    }
+
+    fn focused(&self) -> Option<PaneId> {
+        self.windows.active().focused
+    }
+
+    /// Spawn a pane and split the focused one along `axis` to make room.
+    fn open_pane(&mut self, axis: Axis) -> anyhow::Result<CommandOutcome> {
+        // roughly the size it ends up with, `fit_panes` makes it exact
+        let rect = self.focused_rect().unwrap_or(self.area);
+        let window = self.windows.active_mut();
+        let (rows, cols) = match (window.layout.is_empty(), axis) {
+            (true, _) => (rect.height, rect.width),
+            (false, Axis::Horizontal) => (rect.height, rect.width / 2),
+            (false, Axis::Vertical) => (rect.height / 2, rect.width),
+        };
+        let id = self.loom.spawn_pty(rows, cols)?;
+        let window = self.windows.active_mut();
+        if !window.layout.split(window.focused, id, axis) {
+            self.loom.close_pane(id)?;
+            anyhow::bail!("nowhere to put a new pane");
+        }
+        window.focus(id);
+        Ok(CommandOutcome::Spawned(id))
+    }
+
+    fn focused_rect(&self) -> Option<Rect> {
+        let arranged = self.windows.active().layout.arrange(self.area);
+        let focused = self.focused()?;
+        arranged
+            .into_iter()
+            .find_map(|(id, rect)| (id == focused).then_some(rect))
+    }
+
+    /// Resize every pane's pty to the space its window's layout gives it,
+    /// windows that aren't on screen included.
+    pub fn fit_panes(&mut self) {
+        for window in self.windows.iter() {
+            for (id, rect) in window.arrange(self.area) {
+                if rect.width == 0 || rect.height == 0 {
+                    continue;
+                }
+                // a pty that refuses keeps its old size, nothing else to do
+                _ = self.loom.resize(id, rect.height, rect.width);
+            }
+        }
+    }
+
+    /// Whether the last pane was closed, and with it the session.
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Mark windows that aren't on screen when their panes print or beep,
+    /// and tell about bells there.
+    pub fn collect_alerts(&mut self) {
+        let active = self.windows.active_index();
+        let mut notices = vec![];
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            for id in window.layout.panes() {
+                let Some(pane) = self.loom.pane_mut(id) else {
+                    continue;
+                };
+                let alerts = pane.take_alerts();
+                if i != active {
+                    if alerts.bell && !window.bell {
+                        notices.push(Notice::warn(format!("bell in window {i}, pane {id}")));
+                    }
+                    window.activity |= alerts.activity;
+                    window.bell |= alerts.bell;
+                }
+            }
+        }
+        for notice in notices {
+            self.notify(notice);
+        }
+    }
+
+    /// Tell about panes whose process exited and do what their policy
+    /// says: leave them on screen, close them or start them again.
+    pub fn reap_panes(&mut self) {
+        let exited: Vec<_> = self
+            .loom
+            .panes_mut()
+            .iter_mut()
+            .filter_map(|pane| Some((pane.id(), pane.check_exit()?, pane.exit_policy())))
+            .collect();
+        for (id, exit, policy) in exited {
+            let text = format!("pane {id}: {exit}");
+            self.notify(if exit.failed() {
+                Notice::warn(text)
+            } else {
+                Notice::info(text)
+            });
+            let result = match policy {
+                ExitPolicy::Keep => Ok(()),
+                ExitPolicy::Close => self.dispatch(UserRequestType::ClosePane(id)).map(|_| ()),
+                ExitPolicy::Respawn if exit.runtime < MIN_RESPAWN_RUNTIME => {
+                    self.notify(Notice::warn(format!(
+                        "pane {id} exited too soon to respawn, keeping it open"
+                    )));
+                    Ok(())
+                }
+                ExitPolicy::Respawn => self.loom.respawn(id, false),
+            };
+            if let Err(err) = result {
+                self.notify(Notice::error(format!("pane {id}: {err}")));
+            }
+        }
+    }
+
+    /// Tell every attached client, and keep it for the message log.
+    pub fn notify(&mut self, notice: Notice) {
+        self.notices.push(notice.clone());
+        self.fresh.push(notice);
+    }
+
+    /// Notices since the last call, for the clients.
+    pub fn take_notices(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.fresh)
+    }
+
+    /// Everything told so far, oldest first, for a client that just came.
+    pub fn history(&self) -> Vec<Notice> {
+        self.notices.iter().cloned().collect()
+    }
+
+    /// Look up what runs in each pane, unless that was done very recently.
+    /// It means going through `/proc`, too much to do every frame.
+    pub fn collect_info(&mut self) {
+        if self
+            .info_checked
+            .is_some_and(|at| at.elapsed() < INFO_INTERVAL)
+        {
+            return;
+        }
+        self.info_checked = Some(Instant::now());
+        self.info = self
+            .loom
+            .panes()
+            .iter()
+            .map(|pane| (pane.id(), pane.info()))
+            .collect();
+    }
+
+    /// The tab bar entries, in window order.
+    fn tabs(&self) -> Vec<Tab> {
+        let active = self.windows.active_index();
+        self.windows
+            .iter()
+            .enumerate()
+            .map(|(i, window)| {
+                let title = window
+                    .focused
+                    .and_then(|id| self.loom.pane(id))
+                    .map(|p| p.title());
+                let name = window.name.as_deref().or(title).unwrap_or("empty");
+                let (marker, theme) = if i == active {
+                    ("*", 1)
+                } else if window.bell {
+                    ("!", 2)
+                } else if window.activity {
+                    ("#", 2)
+                } else {
+                    ("", 0)
+                };
+                let zoom = if window.zoomed { "Z" } else { "" };
+                Tab {
+                    label: format!(" {i}:{name}{marker}{zoom} "),
+                    theme,
+                }
+            })
+            .collect()
+    }
+
+    pub fn view(&self) -> View {
+        let window = self.windows.active();
+        View {
+            panes: window.arrange(self.area),
+            focused: window.focused,
+            zoomed: window.zoomed,
+            active: self.windows.active_index(),
+            name: window.name.clone(),
+            tabs: self.tabs(),
+            scroll: window
+                .focused
+                .and_then(|id| self.loom.pane(id))
+                .filter(|pane| pane.in_copy_mode())
+                .map(|pane| pane.scroll_position()),
+            search: window
+                .focused
+                .and_then(|id| self.loom.pane(id))
+                .and_then(|pane| pane.search_status()),
+            info: window.focused.and_then(|id| self.info.get(&id).cloned()),
+            exits: window
+                .layout
+                .panes()
+                .into_iter()
+                .filter_map(|id| Some((id, self.loom.pane(id)?.exit()?.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn info(&self, attached: usize) -> SessionInfo {
+        SessionInfo {
+            windows: self.windows.len(),
+            panes: self.windows.iter().map(|w| w.layout.panes().len()).sum(),
+            attached,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(argv: &[&str]) -> Session {
+        let argv = argv.iter().map(Into::into).collect();
+        Session::new(PseudoTerminalLoom::with_command(argv))
+    }
+
+    // one pane running `argv`, which does `policy` when it exits
+    fn one_pane(argv: &[&str], policy: ExitPolicy) -> (Session, PaneId) {
+        let mut session = session(argv);
+        session.loom.set_exit_policy(policy);
+        let Ok(CommandOutcome::Spawned(id)) = session.dispatch(UserRequestType::NewPane) else {
+            panic!("no pane");
+        };
+        (session, id)
+    }
+
+    /// Reap until `done`, giving up after a few seconds.
+    fn reap_until(session: &mut Session, done: impl Fn(&Session) -> bool) {
+        let started = Instant::now();
+        while !done(session) {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "gave up waiting"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+            session.reap_panes();
+        }
+    }
+
+    fn notices(session: &mut Session) -> Vec<String> {
+        let notices = session.take_notices().into_iter();
+        notices.map(|notice| notice.text).collect()
+    }
+
+    fn tab(session: &Session, index: usize) -> String {
+        session.view().tabs[index].label.clone()
+    }
+
+    #[test]
+    fn a_window_renamed_to_nothing_follows_its_pane_again() {
+        let mut session = session(&["cat"]);
+        session.dispatch(UserRequestType::NewPane).unwrap();
+        assert_eq!(tab(&session, 0), " 0:cat* ");
+        let rename = |name: &str| UserRequestType::RenameWindow {
+            window: 0,
+            name: name.to_string(),
+        };
+        session.dispatch(rename("work")).unwrap();
+        assert_eq!(tab(&session, 0), " 0:work* ");
+        session.dispatch(rename("")).unwrap();
+        assert_eq!(tab(&session, 0), " 0:cat* ");
+        assert!(
+            session
+                .dispatch(UserRequestType::RenameWindow {
+                    window: 1,
+                    name: "nope".to_string(),
+                })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn closing_the_last_window_ends_the_session() {
+        let mut session = session(&["cat"]);
+        session.dispatch(UserRequestType::NewPane).unwrap();
+        session.dispatch(UserRequestType::NewWindow).unwrap();
+        session.dispatch(UserRequestType::NewPane).unwrap();
+        assert_eq!(session.loom().panes().len(), 3);
+
+        session.dispatch(UserRequestType::CloseWindow(1)).unwrap();
+        assert_eq!(session.loom().panes().len(), 1);
+        assert_eq!(session.view().tabs.len(), 1);
+        assert!(!session.is_over());
+        session.dispatch(UserRequestType::CloseWindow(0)).unwrap();
+        assert!(session.loom().panes().is_empty());
+        assert!(session.is_over());
+    }
+
+    #[test]
+    fn a_kept_pane_stays_saying_how_it_went() {
+        let (mut session, id) = one_pane(&["sh", "-c", "exit 3"], ExitPolicy::Keep);
+        reap_until(&mut session, |s| {
+            s.loom().pane(id).unwrap().exit().is_some()
+        });
+        let exit = session.loom().pane(id).unwrap().exit().unwrap().clone();
+        assert_eq!((exit.code, exit.signal.as_deref()), (3, None));
+        assert!(exit.failed());
+        assert_eq!(session.view().exits, [(id, exit)]);
+        assert_eq!(
+            notices(&mut session),
+            ["pane %0: sh exited with code 3 after 0s"]
+        );
+        // it's only told about once
+        session.reap_panes();
+        assert!(notices(&mut session).is_empty());
+        assert!(!session.is_over());
+    }
+
+    #[test]
+    fn a_closing_pane_that_was_the_last_ends_the_session() {
+        let (mut session, id) = one_pane(&["true"], ExitPolicy::Close);
+        reap_until(&mut session, Session::is_over);
+        assert!(session.loom().pane(id).is_none());
+        assert_eq!(notices(&mut session), ["pane %0: true exited after 0s"]);
+    }
+
+    #[test]
+    fn a_pane_that_exits_right_away_isnt_respawned() {
+        let (mut session, id) = one_pane(&["true"], ExitPolicy::Respawn);
+        reap_until(&mut session, |s| {
+            s.loom().pane(id).unwrap().exit().is_some()
+        });
+        assert_eq!(
+            notices(&mut session),
+            [
+                "pane %0: true exited after 0s",
+                "pane %0 exited too soon to respawn, keeping it open",
+            ]
+        );
+        assert!(!session.is_over());
+    }
+
+    #[test]
+    fn a_respawned_pane_keeps_its_id_and_counts_on() {
+        let argv = ["sh", "-c", "sleep 1.2"];
+        let (mut session, id) = one_pane(&argv, ExitPolicy::Respawn);
+        let started = Instant::now();
+        // what it showed right before it was reaped
+        let generation = loop {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "gave up waiting"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+            let generation = session.loom().pane(id).unwrap().generation();
+            session.reap_panes();
+            if !session.fresh.is_empty() {
+                break generation;
+            }
+        };
+        let pane = session.loom().pane(id).unwrap();
+        assert!(pane.exit().is_none());
+        // so clients know to send the new screen
+        assert!(pane.generation() > generation);
+        assert_eq!(session.loom().panes().len(), 1);
+        assert_eq!(notices(&mut session), ["pane %0: sh exited after 1s"]);
+    }
+
+    #[test]
+    fn a_running_pane_is_only_respawned_when_killed_for_it() {
+        let (mut session, id) = one_pane(&["cat"], ExitPolicy::Keep);
+        let err = session.loom.respawn(id, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pane %0 is still running, use respawn-pane -k"
+        );
+        let generation = session.loom().pane(id).unwrap().generation();
+        session.loom.respawn(id, true).unwrap();
+        let pane = session.loom().pane(id).unwrap();
+        assert!(pane.generation() > generation);
+        assert!(pane.exit().is_none());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::clock;
+use anyhow::anyhow;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// What the statusline's segments are filled in from, besides the clock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vars {
+    pub session: String,
+    /// The active window's number.
+    pub window: usize,
+    /// The focused pane, like `%3`.
+    pub pane: String,
+    pub command: String,
+    pub cwd: String,
+    /// The last command's exit status. Success shows as nothing, so
+    /// `{?status ...}` only shows up after a failure.
+    pub status: Option<i32>,
+    /// How the focused pane's process ended, once it has.
+    pub exit: String,
+    /// `prefix` while the prefix waits for a command key, `copy` in copy
+    /// mode, otherwise nothing.
+    pub mode: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Var {
+    Session,
+    Window,
+    Pane,
+    Command,
+    Cwd,
+    Status,
+    Exit,
+    Mode,
+    Time,
+    Date,
+}
+
+impl Var {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "session" => Self::Session,
+            "window" => Self::Window,
+            "pane" => Self::Pane,
+            "command" => Self::Command,
+            "cwd" => Self::Cwd,
+            "status" => Self::Status,
+            "exit" => Self::Exit,
+            "mode" => Self::Mode,
+            "time" => Self::Time,
+            "date" => Self::Date,
+            _ => return Err(anyhow!("unknown variable {name:?}")),
+        })
+    }
+
+    fn value(self, vars: &Vars, clock: &Clock) -> String {
+        match self {
+            Self::Session => vars.session.clone(),
+            Self::Window => vars.window.to_string(),
+            Self::Pane => vars.pane.clone(),
+            Self::Command => vars.command.clone(),
+            Self::Cwd => home_as_tilde(&vars.cwd),
+            Self::Status => match vars.status {
+                Some(0) | None => String::new(),
+                Some(status) => status.to_string(),
+            },
+            Self::Exit => vars.exit.clone(),
+            Self::Mode => vars.mode.to_string(),
+            Self::Time => clock.time.clone(),
+            Self::Date => clock.date.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    /// `{name}`, or `{name:N}` to show at most N characters of it.
+    Var(Var, Option<usize>),
+    /// `{?name ...}`: what follows the name, only if the value isn't empty.
+    If(Var, Format),
+}
+
+/// A statusline segment's text with `{...}` in it to fill in, see
+/// [`Format::parse`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Format(Vec<Piece>);
+
+impl Format {
+    /// `{name}` is replaced by a variable's value and `{name:N}` keeps at
+    /// most N characters of it, cutting from the front. `{?name text}`
+    /// shows `text`, which can have more of these in it, only when the
+    /// variable isn't empty. `{{` and `}}` are literal braces.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        parse_pieces(&mut s.chars().peekable(), false)
+    }
+
+    pub fn render(&self, vars: &Vars, clock: &Clock) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Var(var, width) => {
+                    let value = var.value(vars, clock);
+                    match width {
+                        Some(width) => out.push_str(&shorten(&value, *width)),
+                        None => out.push_str(&value),
+                    }
+                }
+                Piece::If(var, body) => {
+                    if !var.value(vars, clock).is_empty() {
+                        out.push_str(&body.render(vars, clock));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Whether the time or date is in it, so it changes as time passes.
+    pub fn uses_clock(&self) -> bool {
+        self.0.iter().any(|piece| match piece {
+            Piece::Text(_) => false,
+            Piece::Var(var, _) => matches!(var, Var::Time | Var::Date),
+            Piece::If(var, body) => matches!(var, Var::Time | Var::Date) || body.uses_clock(),
+        })
+    }
+}
+
+fn parse_pieces(chars: &mut Peekable<Chars<'_>>, nested: bool) -> anyhow::Result<Format> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    loop {
+        let c = match chars.next() {
+            Some(c) => c,
+            None if nested => return Err(anyhow!("a {{?...}} is missing its closing }}")),
+            None => break,
+        };
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                text.push(c);
+            }
+            ('}', _) if nested => break,
+            ('}', _) => return Err(anyhow!("}} without a {{, write }}}} for a brace")),
+            ('{', _) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(parse_placeholder(chars)?);
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(Format(pieces))
+}
+
+// after the opening brace
+fn parse_placeholder(chars: &mut Peekable<Chars<'_>>) -> anyhow::Result<Piece> {
+    let conditional = chars.next_if_eq(&'?').is_some();
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_') {
+        name.push(c);
+    }
+    let var = Var::parse(&name)?;
+
+    if conditional {
+        return match chars.next() {
+            Some('}') => Ok(Piece::If(var, Format::default())),
+            Some(' ') => Ok(Piece::If(var, parse_pieces(chars, true)?)),
+            _ => Err(anyhow!("expected a space after {{?{name}")),
+        };
+    }
+    match chars.next() {
+        Some('}') => Ok(Piece::Var(var, None)),
+        Some(':') => {
+            let mut width = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                width.push(c);
+            }
+            match (width.parse(), chars.next()) {
+                (Ok(width), Some('}')) => Ok(Piece::Var(var, Some(width))),
+                _ => Err(anyhow!("expected a width like {{{name}:20}}")),
+            }
+        }
+        _ => Err(anyhow!("{{{name} is missing its closing }}")),
+    }
+}
+
+/// The last `width` characters of `s`, with an ellipsis where the rest
+/// was. The end of a path or command says more than its start.
+fn shorten(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    if len <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let tail: String = s.chars().skip(len - width + 1).collect();
+    format!("…{tail}")
+}
+
+fn home_as_tilde(path: &str) -> String {
+    match std::env::var("HOME") {
+        Ok(home) if !home.is_empty() && path.starts_with(&home) => match &path[home.len()..] {
+            rest if rest.is_empty() || rest.starts_with('/') => format!("~{rest}"),
+            _ => path.to_string(),
+        },
+        _ => path.to_string(),
+    }
+}
+
+/// The local date and time, as `{date}` and `{time}` show them.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    date: String,
+    time: String,
+}
+
+impl Clock {
+    pub fn now() -> Self {
+        Self::at(clock::current_second())
+    }
+
+    /// The clock as it read `secs` seconds after the epoch.
+    pub fn at(secs: u64) -> Self {
+        // SAFETY: localtime_r only writes to the tm it's given
+        let tm = unsafe {
+            let secs = secs as libc::time_t;
+            let mut tm = std::mem::zeroed::<libc::tm>();
+            libc::localtime_r(&secs, &mut tm);
+            tm
+        };
+        Self {
+            date: format!(
+                "{:04}-{:02}-{:02}",
+                tm.tm_year + 1900,
+                tm.tm_mon + 1,
+                tm.tm_mday
+            ),
+            time: format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec),
+        }
+    }
+
+    pub fn time(&self) -> &str {
+        &self.time
+    }
+}
+
+/// One part of the statusline's right side, with its colours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub format: Format,
+    pub fg: String,
+    pub bg: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: &str, vars: &Vars) -> String {
+        Format::parse(format).unwrap().render(vars, &Clock::at(0))
+    }
+
+    fn error(format: &str) -> String {
+        Format::parse(format).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn variables_are_filled_in_and_braces_escaped() {
+        let vars = Vars {
+            session: "work".to_string(),
+            window: 2,
+            pane: "%3".to_string(),
+            ..Vars::default()
+        };
+        assert_eq!(render("{session}:{window} {pane}", &vars), "work:2 %3");
+        assert_eq!(render("{{{session}}}", &vars), "{work}");
+        assert!(!Format::parse("{session}").unwrap().uses_clock());
+        assert!(Format::parse("{?session {time}}").unwrap().uses_clock());
+    }
+
+    #[test]
+    fn a_width_keeps_the_end() {
+        let vars = Vars {
+            command: "cargo build --release".to_string(),
+            ..Vars::default()
+        };
+        assert_eq!(render("{command:9}", &vars), "…-release");
+        assert_eq!(render("{command:40}", &vars), "cargo build --release");
+        assert_eq!(render("[{command:0}]", &vars), "[]");
+    }
+
+    #[test]
+    fn conditionals_show_only_with_a_value_and_nest() {
+        let mut vars = Vars {
+            status: Some(0),
+            ..Vars::default()
+        };
+        // a closing brace right before another would be an escaped one
+        let format = "{?status {?mode {mode}: }failed {status}}!";
+        assert_eq!(render(format, &vars), "!");
+        vars.status = Some(2);
+        assert_eq!(render(format, &vars), "failed 2!");
+        vars.mode = "copy";
+        assert_eq!(render(format, &vars), "copy: failed 2!");
+        assert_eq!(render("{?mode}", &vars), "");
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        assert_eq!(error("{nope}"), "unknown variable \"nope\"");
+        assert_eq!(error("{?mode:5}"), "expected a space after {?mode");
+        assert_eq!(
+            error("{?mode in {mode}"),
+            "a {?...} is missing its closing }"
+        );
+        assert_eq!(error("}"), "} without a {, write }} for a brace");
+        assert_eq!(error("{pane:x}"), "expected a width like {pane:20}");
+        assert_eq!(error("{pane"), "{pane is missing its closing }");
+    }
+}
@@ -0,0 +1,264 @@
+use crate::layout::{Layout, Rect};
+use crate::loom::PaneId;
+
+/// A full screen's worth of panes, shown one at a time like tabs.
+#[derive(Debug, Default)]
+pub struct Window {
+    /// Set by renaming, otherwise the focused pane's title is used.
+    pub name: Option<String>,
+    pub layout: Layout,
+    pub focused: Option<PaneId>,
+    /// The pane focused before `focused`, for `last-pane`.
+    pub last: Option<PaneId>,
+    /// The focused pane fills the window, the others wait where the
+    /// layout keeps them until it's put back.
+    pub zoomed: bool,
+    /// Something happened in a pane while the window wasn't on screen.
+    pub activity: bool,
+    pub bell: bool,
+}
+
+impl Window {
+    /// Give `pane` the focus, remembering which pane had it.
+    pub fn focus(&mut self, pane: PaneId) {
+        if self.focused != Some(pane) {
+            self.last = self.focused;
+            self.focused = Some(pane);
+            // another pane can't be looked at behind the zoomed one
+            self.zoomed = false;
+        }
+    }
+
+    /// Take `pane` out of the layout. If it had the focus, the pane that
+    /// had it before gets it back.
+    pub fn remove(&mut self, pane: PaneId) {
+        self.layout.remove(pane);
+        self.zoomed = false;
+        if self.last == Some(pane) {
+            self.last = None;
+        }
+        if self.focused == Some(pane) {
+            self.focused = self
+                .last
+                .take()
+                .or_else(|| self.layout.panes().last().copied());
+        }
+    }
+
+    /// Where every pane on screen goes in `area`: the layout's panes, or
+    /// only the focused one while it's zoomed.
+    pub fn arrange(&self, area: Rect) -> Vec<(PaneId, Rect)> {
+        match self.focused {
+            Some(focused) if self.zoomed => vec![(focused, area)],
+            _ => self.layout.arrange(area),
+        }
+    }
+
+    fn clear_alerts(&mut self) {
+        self.activity = false;
+        self.bell = false;
+    }
+}
+
+/// The windows of a session, in tab bar order, one of them active.
+#[derive(Debug)]
+pub struct Windows {
+    list: Vec<Window>,
+    active: usize,
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Self {
+            list: vec![Window::default()],
+            active: 0,
+        }
+    }
+}
+
+impl Windows {
+    pub fn iter(&self) -> impl Iterator<Item = &Window> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Window> {
+        self.list.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Window {
+        &self.list[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Window {
+        &mut self.list[self.active]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Window> {
+        self.list.get_mut(index)
+    }
+
+    /// Add an empty window after the active one and switch to it.
+    pub fn open(&mut self) -> usize {
+        self.active += 1;
+        self.list.insert(self.active, Window::default());
+        self.active
+    }
+
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.list.len() {
+            return false;
+        }
+        self.active = index;
+        self.list[index].clear_alerts();
+        true
+    }
+
+    /// Select the window `offset` steps away, wrapping around.
+    pub fn cycle(&mut self, offset: isize) -> usize {
+        let len = self.list.len() as isize;
+        let index = (self.active as isize + offset).rem_euclid(len) as usize;
+        self.select(index);
+        index
+    }
+
+    /// Swap the active window with its neighbour `offset` steps away,
+    /// keeping it active. Returns where it ended up.
+    pub fn shift(&mut self, offset: isize) -> usize {
+        let len = self.list.len() as isize;
+        let index = (self.active as isize + offset).rem_euclid(len) as usize;
+        self.list.swap(self.active, index);
+        self.active = index;
+        index
+    }
+
+    /// Take the window out of the list. The last window is never removed,
+    /// it is emptied instead so there is always something to show.
+    pub fn remove(&mut self, index: usize) -> Option<Window> {
+        if index >= self.list.len() {
+            return None;
+        }
+        if self.list.len() == 1 {
+            return Some(std::mem::take(&mut self.list[0]));
+        }
+        let window = self.list.remove(index);
+        if self.active > index || self.active == self.list.len() {
+            self.active -= 1;
+        }
+        self.list[self.active].clear_alerts();
+        Some(window)
+    }
+
+    /// The window holding `pane`.
+    pub fn find(&self, pane: PaneId) -> Option<usize> {
+        self.list
+            .iter()
+            .position(|window| window.layout.panes().contains(&pane))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Axis;
+
+    // windows named after where they were opened, the last one active
+    fn windows(count: usize) -> Windows {
+        let mut windows = Windows::default();
+        windows.active_mut().name = Some("0".to_string());
+        for i in 1..count {
+            windows.open();
+            windows.active_mut().name = Some(i.to_string());
+        }
+        windows
+    }
+
+    fn names(windows: &Windows) -> Vec<&str> {
+        windows
+            .iter()
+            .map(|window| window.name.as_deref().unwrap_or(""))
+            .collect()
+    }
+
+    #[test]
+    fn cycling_wraps_around_and_clears_alerts() {
+        let mut windows = windows(3);
+        windows.list[0].bell = true;
+        assert_eq!(windows.cycle(1), 0);
+        assert!(!windows.active().bell);
+        assert_eq!(windows.cycle(-1), 2);
+        assert_eq!(windows.cycle(4), 0);
+        assert_eq!(windows.cycle(-7), 2);
+    }
+
+    #[test]
+    fn shifting_swaps_the_active_window_along() {
+        let mut windows = windows(3);
+        assert_eq!(windows.shift(-1), 1);
+        assert_eq!(names(&windows), ["0", "2", "1"]);
+        assert_eq!(windows.active().name.as_deref(), Some("2"));
+        // past the end it swaps with the other end
+        windows.select(0);
+        assert_eq!(windows.shift(-1), 2);
+        assert_eq!(names(&windows), ["1", "2", "0"]);
+        assert_eq!(windows.active_index(), 2);
+    }
+
+    #[test]
+    fn removing_keeps_the_same_window_active() {
+        let mut windows = windows(4);
+        windows.select(2);
+        windows.remove(0);
+        assert_eq!(names(&windows), ["1", "2", "3"]);
+        assert_eq!(windows.active().name.as_deref(), Some("2"));
+        windows.remove(2);
+        assert_eq!(windows.active().name.as_deref(), Some("2"));
+        // the active one goes, the one before it takes over
+        windows.remove(1);
+        assert_eq!(names(&windows), ["1"]);
+        assert_eq!(windows.active_index(), 0);
+        assert!(windows.remove(1).is_none());
+    }
+
+    #[test]
+    fn the_last_window_is_emptied_rather_than_removed() {
+        let mut windows = windows(1);
+        windows
+            .active_mut()
+            .layout
+            .split(None, PaneId::from_raw(0), Axis::Horizontal);
+        let removed = windows.remove(0).unwrap();
+        assert_eq!(removed.name.as_deref(), Some("0"));
+        assert_eq!(removed.layout.panes(), [PaneId::from_raw(0)]);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows.active().name, None);
+        assert!(windows.active().layout.is_empty());
+    }
+
+    #[test]
+    fn closing_the_focused_pane_focuses_the_one_before() {
+        let (a, b, c) = (
+            PaneId::from_raw(0),
+            PaneId::from_raw(1),
+            PaneId::from_raw(2),
+        );
+        let mut window = Window::default();
+        window.layout.split(None, a, Axis::Horizontal);
+        window.layout.split(Some(a), b, Axis::Horizontal);
+        window.layout.split(Some(b), c, Axis::Horizontal);
+        window.focus(c);
+        window.focus(a);
+        window.remove(a);
+        assert_eq!(window.focused, Some(c));
+        // with nothing to go back to, the last pane in the layout
+        window.remove(c);
+        assert_eq!(window.focused, Some(b));
+    }
+}